@@ -0,0 +1,395 @@
+//! Settings read from `config.toml` in XDG config directory
+
+use std::{env, fs, ops::RangeInclusive, path::PathBuf, time::Duration};
+
+use toml::{Table, Value};
+use toml_edit::DocumentMut;
+
+use crate::{
+    core::{LOCK_DELAY, MAX_PREVIEW},
+    keymap::{Key, Keymap, ACTIONS, PRESET_NAMES},
+    pieces::{self, PieceSet, TETROMINO_SET},
+    rotation::{KICKS_180_NAMES, ROTATION_NAMES},
+    tui::{Theme, THEME_NAMES},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+
+const DEFAULT_DAS: Duration = Duration::from_millis(122);
+const DEFAULT_ARR: Duration = Duration::from_millis(5);
+const DEFAULT_SDF: u32 = 20;
+const DEFAULT_PREVIEW: usize = 6;
+/// longest delay accepted for handling and lock delay
+pub const MAX_MILLIS: i64 = 10_000;
+
+/// `$XDG_CONFIG_HOME/tet-rs`, falls back to `~/.config/tet-rs`
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("tet-rs"))
+}
+
+#[derive(Debug, Clone)]
+pub struct Handling {
+    /// delayed auto shift, time before auto shift starts
+    pub das: Duration,
+    /// auto repeat rate, time between auto shifts
+    /// zero moves to the wall at once
+    pub arr: Duration,
+    /// soft drop factor, soft drop is this many times faster than gravity
+    pub sdf: u32,
+    /// DAS cut delay, auto shift waits this long after new piece spawned
+    pub dcd: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// name of rotation system, `--rotation` overrides it
+    pub rotation: String,
+    /// name of 180 kicks, `--kicks-180` overrides it
+    pub kicks_180: String,
+    pub lock_delay: Duration,
+    /// name of piece set, `--pieces` overrides it
+    pub pieces: String,
+    /// pieces of the set, loaded with config
+    pub piece_set: PieceSet,
+}
+
+/// elements of stats panel shown while playing
+#[derive(Debug, Clone)]
+pub struct StatsPanel {
+    pub time: bool,
+    pub pieces: bool,
+    /// pieces per second
+    pub pps: bool,
+    pub lines: bool,
+    pub score: bool,
+    pub level: bool,
+    /// attack per minute
+    pub apm: bool,
+    /// keys per piece
+    pub kpp: bool,
+    /// finesse faults
+    pub finesse: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Visual {
+    /// number of next pieces shown
+    pub preview: usize,
+    pub ghost: bool,
+    pub theme: Theme,
+    /// read from its own `[stats]` table
+    pub stats: StatsPanel,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub handling: Handling,
+    pub rules: Rules,
+    pub visual: Visual,
+    pub keys: Keymap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            handling: Handling {
+                das: DEFAULT_DAS,
+                arr: DEFAULT_ARR,
+                sdf: DEFAULT_SDF,
+                dcd: Duration::ZERO,
+            },
+            rules: Rules {
+                rotation: ROTATION_NAMES[0].to_string(),
+                kicks_180: KICKS_180_NAMES[0].to_string(),
+                lock_delay: LOCK_DELAY,
+                pieces: TETROMINO_SET.to_string(),
+                piece_set: PieceSet::default(),
+            },
+            visual: Visual {
+                preview: DEFAULT_PREVIEW,
+                ghost: true,
+                theme: Theme::default(),
+                stats: StatsPanel {
+                    time: true,
+                    pieces: true,
+                    pps: true,
+                    lines: true,
+                    score: true,
+                    level: true,
+                    apm: true,
+                    kpp: true,
+                    finesse: true,
+                },
+            },
+            keys: Keymap::default(),
+        }
+    }
+}
+
+/// keys of one table, errors name them as `table.key`
+struct Section<'a> {
+    name: &'static str,
+    table: Option<&'a Table>,
+}
+
+impl<'a> Section<'a> {
+    fn new(config: &'a Table, name: &'static str, keys: &[&str]) -> Result<Section<'a>, String> {
+        let table = match config.get(name) {
+            Some(Value::Table(table)) => Some(table),
+            Some(_) => return Err(format!("{} should be a table", name)),
+            None => None,
+        };
+        let unknown =
+            table.and_then(|table| table.keys().find(|key| !keys.contains(&key.as_str())));
+        if let Some(key) = unknown {
+            return Err(format!("unknown key {}.{}", name, key));
+        }
+        Ok(Section { name, table })
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.table?.get(key)
+    }
+
+    fn integer(&self, key: &str, range: RangeInclusive<i64>, default: i64) -> Result<i64, String> {
+        match self.get(key) {
+            None => Ok(default),
+            Some(Value::Integer(value)) if range.contains(value) => Ok(*value),
+            Some(value) => Err(format!(
+                "{}.{} should be an integer from {} to {}, not {}",
+                self.name,
+                key,
+                range.start(),
+                range.end(),
+                value
+            )),
+        }
+    }
+
+    /// duration written in milliseconds
+    fn millis(&self, key: &str, default: Duration) -> Result<Duration, String> {
+        let millis = self.integer(key, 0..=MAX_MILLIS, default.as_millis() as i64)?;
+        Ok(Duration::from_millis(millis as u64))
+    }
+
+    fn boolean(&self, key: &str, default: bool) -> Result<bool, String> {
+        match self.get(key) {
+            None => Ok(default),
+            Some(Value::Boolean(value)) => Ok(*value),
+            Some(value) => Err(format!(
+                "{}.{} should be true or false, not {}",
+                self.name, key, value
+            )),
+        }
+    }
+
+    /// one key or list of keys, None when not given
+    fn keys(&self, key: &str) -> Result<Option<Vec<Key>>, String> {
+        let values = match self.get(key) {
+            None => return Ok(None),
+            Some(Value::Array(values)) => values.iter().collect(),
+            Some(value) => vec![value],
+        };
+        values
+            .into_iter()
+            .map(|value| {
+                value.as_str().and_then(Key::parse).ok_or_else(|| {
+                    format!(
+                        "{}.{} should be a key or list of keys like \"ctrl+z\", not {}",
+                        self.name, key, value
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    fn string(&self, key: &str, default: &str) -> Result<String, String> {
+        match self.get(key) {
+            None => Ok(default.to_string()),
+            Some(Value::String(value)) => Ok(value.clone()),
+            Some(value) => Err(format!(
+                "{}.{} should be a string, not {}",
+                self.name, key, value
+            )),
+        }
+    }
+
+    /// string which is one of `choices`, compared case-insensitively
+    fn choice(&self, key: &str, choices: &[&str], default: &str) -> Result<String, String> {
+        match self.get(key) {
+            None => Ok(default.to_string()),
+            Some(Value::String(value))
+                if choices
+                    .iter()
+                    .any(|choice| choice.eq_ignore_ascii_case(value)) =>
+            {
+                Ok(value.to_lowercase())
+            }
+            Some(value) => Err(format!(
+                "{}.{} should be one of {}, not {}",
+                self.name,
+                key,
+                choices.join(", "),
+                value
+            )),
+        }
+    }
+}
+
+impl Config {
+    /// read config file, defaults are used when it doesn't exist
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = config_dir().map(|dir| dir.join(CONFIG_FILE)) else {
+            return Ok(Config::default());
+        };
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+        Config::parse(&text).map_err(|e| format!("invalid config {}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Config, String> {
+        let config: Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        if let Some(name) = config
+            .keys()
+            .find(|name| !["handling", "rules", "visual", "stats", "keys"].contains(&name.as_str()))
+        {
+            return Err(format!("unknown table {}", name));
+        }
+        let default = Config::default();
+
+        let handling = Section::new(&config, "handling", &["das", "arr", "sdf", "dcd"])?;
+        let handling = Handling {
+            das: handling.millis("das", default.handling.das)?,
+            arr: handling.millis("arr", default.handling.arr)?,
+            sdf: handling.integer("sdf", 1..=1000, default.handling.sdf as i64)? as u32,
+            dcd: handling.millis("dcd", default.handling.dcd)?,
+        };
+
+        let rules = Section::new(
+            &config,
+            "rules",
+            &["rotation", "kicks_180", "lock_delay", "pieces"],
+        )?;
+        let piece_set_name = rules.string("pieces", &default.rules.pieces)?;
+        let rules = Rules {
+            rotation: rules.choice("rotation", &ROTATION_NAMES, &default.rules.rotation)?,
+            kicks_180: rules.choice("kicks_180", &KICKS_180_NAMES, &default.rules.kicks_180)?,
+            lock_delay: rules.millis("lock_delay", default.rules.lock_delay)?,
+            piece_set: pieces::load(&piece_set_name).map_err(|e| format!("rules.pieces: {}", e))?,
+            pieces: piece_set_name,
+        };
+
+        let names = [
+            "time", "pieces", "pps", "lines", "score", "level", "apm", "kpp", "finesse",
+        ];
+        let stats = Section::new(&config, "stats", &names)?;
+        let shown = &default.visual.stats;
+        let stats = StatsPanel {
+            time: stats.boolean("time", shown.time)?,
+            pieces: stats.boolean("pieces", shown.pieces)?,
+            pps: stats.boolean("pps", shown.pps)?,
+            lines: stats.boolean("lines", shown.lines)?,
+            score: stats.boolean("score", shown.score)?,
+            level: stats.boolean("level", shown.level)?,
+            apm: stats.boolean("apm", shown.apm)?,
+            kpp: stats.boolean("kpp", shown.kpp)?,
+            finesse: stats.boolean("finesse", shown.finesse)?,
+        };
+
+        let visual = Section::new(&config, "visual", &["preview", "ghost", "theme"])?;
+        let theme = visual.choice("theme", &THEME_NAMES, THEME_NAMES[0])?;
+        let visual = Visual {
+            preview: visual.integer(
+                "preview",
+                0..=MAX_PREVIEW as i64,
+                default.visual.preview as i64,
+            )? as usize,
+            ghost: visual.boolean("ghost", default.visual.ghost)?,
+            theme: Theme::from_name(&theme).unwrap_or_default(),
+            stats,
+        };
+
+        // keys given for an action replace keys of preset
+        let names: Vec<&str> = ["preset"]
+            .into_iter()
+            .chain(ACTIONS.iter().map(|(_, name)| *name))
+            .collect();
+        let keys = Section::new(&config, "keys", &names)?;
+        let preset = keys.choice("preset", &PRESET_NAMES, PRESET_NAMES[0])?;
+        let mut keymap = Keymap::preset(&preset).unwrap_or_default();
+        let mut given: Vec<(usize, Vec<Key>)> = Vec::new();
+        for (index, (_, name)) in ACTIONS.iter().enumerate() {
+            let Some(bound) = keys.keys(name)? else {
+                continue;
+            };
+            for key in &bound {
+                if let Some((other, _)) = given.iter().find(|(_, keys)| keys.contains(key)) {
+                    return Err(format!(
+                        "keys.{} and keys.{} both use {}",
+                        ACTIONS[*other].1, name, key
+                    ));
+                }
+            }
+            keymap.clear(index);
+            bound.iter().for_each(|key| keymap.bind(index, *key));
+            given.push((index, bound));
+        }
+
+        Ok(Config {
+            handling,
+            rules,
+            visual,
+            keys: keymap,
+        })
+    }
+
+    /// write settings to config file, comments and stats panel are kept
+    pub fn save(&self) -> Result<(), String> {
+        let dir = config_dir().ok_or("can't find config directory")?;
+        fs::create_dir_all(&dir).map_err(|e| format!("can't create {}: {}", dir.display(), e))?;
+        let path = dir.join(CONFIG_FILE);
+        let text = if path.exists() {
+            fs::read_to_string(&path)
+                .map_err(|e| format!("can't read {}: {}", path.display(), e))?
+        } else {
+            String::new()
+        };
+        let mut document: DocumentMut = text
+            .parse()
+            .map_err(|e| format!("invalid config {}: {}", path.display(), e))?;
+        for name in ["handling", "rules", "visual"] {
+            // tables added here would be written inline otherwise
+            document.entry(name).or_insert(toml_edit::table());
+        }
+        let millis = |duration: Duration| toml_edit::value(duration.as_millis() as i64);
+        document["handling"]["das"] = millis(self.handling.das);
+        document["handling"]["arr"] = millis(self.handling.arr);
+        document["handling"]["sdf"] = toml_edit::value(self.handling.sdf as i64);
+        document["handling"]["dcd"] = millis(self.handling.dcd);
+        document["rules"]["rotation"] = toml_edit::value(&self.rules.rotation);
+        document["rules"]["kicks_180"] = toml_edit::value(&self.rules.kicks_180);
+        document["rules"]["lock_delay"] = millis(self.rules.lock_delay);
+        document["rules"]["pieces"] = toml_edit::value(&self.rules.pieces);
+        document["visual"]["preview"] = toml_edit::value(self.visual.preview as i64);
+        document["visual"]["ghost"] = toml_edit::value(self.visual.ghost);
+        document["visual"]["theme"] = toml_edit::value(self.visual.theme.name());
+
+        let mut table = toml_edit::Table::new();
+        for (index, (_, name)) in ACTIONS.iter().enumerate() {
+            let bound: toml_edit::Array =
+                self.keys.keys(index).iter().map(Key::to_string).collect();
+            table[name] = toml_edit::value(bound);
+        }
+        document["keys"] = toml_edit::Item::Table(table);
+        fs::write(&path, document.to_string())
+            .map_err(|e| format!("can't write {}: {}", path.display(), e))
+    }
+}
@@ -1,66 +1,191 @@
 use std::{
     sync::{mpsc::Sender, Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use crossterm::event;
+use crossterm::event::{self, KeyCode, KeyModifiers};
 
-use crate::{ControlKind, DAS_TIMEOUT};
+use crate::{config::Config, ControlKind, Event};
 
 const KEY_TIMEOUT: Duration = Duration::from_secs(100);
+/// key is taken as released when its first repeat doesn't come in time,
+/// longer than usual delay before key repeat starts
+const FIRST_REPEAT_TIMEOUT: Duration = Duration::from_millis(700);
+/// presses closer than this are taps, OS key repeat doesn't start sooner
+const MIN_REPEAT_DELAY: Duration = Duration::from_millis(200);
+/// key is taken as released when repeating stops for this long
+const REPEAT_TIMEOUT: Duration = Duration::from_millis(100);
+/// time given to game to pause before terminal is handed back to shell
+const SUSPEND_TIMEOUT: Duration = Duration::from_millis(200);
 
-pub fn handle_controls(tx: Sender<ControlKind>) {
+/// key held down, guessed from key repeat when terminal doesn't report releases
+#[derive(Debug, Clone, Copy)]
+struct Held {
+    since: Instant,
+    last_press: Instant,
+    repeating: bool,
+}
+
+/// sends raw key and mouse events along with controls mapped from them
+/// handling and keys in `config` are read on each key press, so reloading takes effect
+/// `key_release` tells whether terminal reports key releases
+pub fn handle_controls(tx: Sender<Event>, config: Arc<Mutex<Config>>, key_release: bool) {
     let das_scan_left = Arc::new(Mutex::new(false));
     let das_scan_right = Arc::new(Mutex::new(false));
+    let held_left = Arc::new(Mutex::new(None));
+    let held_right = Arc::new(Mutex::new(None));
+    let held_soft_drop = Arc::new(Mutex::new(None));
     loop {
         if event::poll(KEY_TIMEOUT).expect("poll error") {
-            if let event::Event::Key(key) = event::read().unwrap() {
-                use event::KeyCode::*;
+            let event = event::read().unwrap();
+            if let event::Event::Mouse(mouse) = event {
+                tx.send(Event::Mouse(mouse))
+                    .expect("can't send mouse events")
+            } else if let event::Event::FocusLost = event {
+                // keys held while switching away are never released
+                stop_das(&das_scan_left);
+                stop_das(&das_scan_right);
+                for held in [&held_left, &held_right, &held_soft_drop] {
+                    *held.lock().unwrap() = None;
+                }
+                for control in [
+                    ControlKind::LeftDasEnd,
+                    ControlKind::RightDasEnd,
+                    ControlKind::SoftDropEnd,
+                ] {
+                    tx.send(Event::Control(control))
+                        .expect("can't send focus events");
+                }
+                tx.send(Event::FocusLost).expect("can't send focus events");
+            } else if let event::Event::Key(key) = event {
+                tx.send(Event::Key(key)).expect("can't send key events");
                 use event::KeyEventKind::*;
-                let control = match (key.kind, key.code) {
-                    (Press, Char('q')) => Some(ControlKind::Quit),
-                    (Press, Char('j')) => Some(ControlKind::SoftDrop),
-                    (Press, Char(' ')) => Some(ControlKind::HardDrop),
-                    (Press, Char('y')) => Some(ControlKind::Hold),
-                    (Press, Char('a')) => Some(ControlKind::RotateCC),
-                    (Press, Char('s')) => Some(ControlKind::Rotate180),
-                    (Press, Char('d')) => Some(ControlKind::Rotate),
-                    (Press, Char('h')) => {
-                        das_timeout(&tx, &das_scan_left, ControlKind::LeftDasStart);
+                let (das, control) = {
+                    let config = config.lock().unwrap();
+                    (config.handling.das, config.keys.control(&key))
+                };
+                // raw mode keeps terminal from stopping us on ctrl+z
+                let suspend = KeyCode::Char('z');
+                if key.kind == Press
+                    && key.code == suspend
+                    && key.modifiers.contains(KeyModifiers::CONTROL)
+                    && control.is_none()
+                {
+                    raise_suspend();
+                    continue;
+                }
+                let control = match (key.kind, control) {
+                    (Press, Some(ControlKind::Left)) if !key_release => {
+                        repeat_press(&tx, &held_left, das, ControlKind::Left);
+                        None
+                    }
+                    (Press, Some(ControlKind::Right)) if !key_release => {
+                        repeat_press(&tx, &held_right, das, ControlKind::Right);
+                        None
+                    }
+                    (Press, Some(ControlKind::SoftDrop)) if !key_release => {
+                        repeat_press(&tx, &held_soft_drop, das, ControlKind::SoftDrop);
+                        None
+                    }
+                    (Press, Some(ControlKind::Left)) => {
+                        das_timeout(&tx, &das_scan_left, das, ControlKind::LeftDasStart);
                         Some(ControlKind::Left)
                     }
-                    (Press, Char('l')) => {
-                        das_timeout(&tx, &das_scan_right, ControlKind::RightDasStart);
+                    (Press, Some(ControlKind::Right)) => {
+                        das_timeout(&tx, &das_scan_right, das, ControlKind::RightDasStart);
                         Some(ControlKind::Right)
                     }
-                    (Release, Char('h')) => {
+                    (Release, Some(ControlKind::Left)) => {
                         stop_das(&das_scan_left);
                         Some(ControlKind::LeftDasEnd)
                     }
-                    (Release, Char('l')) => {
+                    (Release, Some(ControlKind::Right)) => {
                         stop_das(&das_scan_right);
                         Some(ControlKind::RightDasEnd)
                     }
+                    (Release, Some(ControlKind::SoftDrop)) => Some(ControlKind::SoftDropEnd),
+                    (Press, control) => control,
                     _ => None,
                 };
                 if let Some(control) = control {
-                    tx.send(control).expect("can't send key events")
+                    tx.send(Event::Control(control))
+                        .expect("can't send key events")
                 }
             }
         }
     }
 }
 
-fn das_timeout(tx: &Sender<ControlKind>, is_scanning: &Arc<Mutex<bool>>, control: ControlKind) {
+#[cfg(unix)]
+fn raise_suspend() {
+    let _ = signal_hook::low_level::raise(signal_hook::consts::SIGTSTP);
+}
+
+#[cfg(not(unix))]
+fn raise_suspend() {}
+
+/// pauses game and restores terminal on SIGTSTP, takes terminal again on SIGCONT
+#[cfg(unix)]
+pub fn handle_signals(tx: Sender<Event>) {
+    use signal_hook::{
+        consts::{SIGCONT, SIGTSTP},
+        iterator::Signals,
+        low_level,
+    };
+    use std::sync::mpsc;
+
+    use crate::tui;
+
+    let Ok(mut signals) = Signals::new([SIGTSTP, SIGCONT]) else {
+        return;
+    };
+    for signal in signals.forever() {
+        if signal == SIGCONT {
+            tui::resume().expect("can't take terminal");
+            if tx.send(Event::Resumed).is_err() {
+                return;
+            }
+        } else {
+            // game is paused and done drawing before terminal is restored
+            let (ack_tx, ack_rx) = mpsc::channel();
+            if tx.send(Event::Suspend(ack_tx)).is_err() {
+                return;
+            }
+            let _ = ack_rx.recv_timeout(SUSPEND_TIMEOUT);
+            tui::suspend().expect("can't restore terminal");
+            // stops process until SIGCONT, which resumes in the next round
+            let _ = low_level::emulate_default_handler(SIGTSTP);
+        }
+    }
+}
+
+/// left button paints, right button erases
+/// returns Some(erase) when mouse event paints cell
+pub fn paint_button(mouse: &event::MouseEvent) -> Option<bool> {
+    use event::MouseButton::*;
+    use event::MouseEventKind::*;
+    match mouse.kind {
+        Down(Left) | Drag(Left) => Some(false),
+        Down(Right) | Drag(Right) => Some(true),
+        _ => None,
+    }
+}
+
+fn das_timeout(
+    tx: &Sender<Event>,
+    is_scanning: &Arc<Mutex<bool>>,
+    das: Duration,
+    control: ControlKind,
+) {
     let is_scanning = Arc::clone(&is_scanning);
     if !*is_scanning.lock().unwrap() {
         *is_scanning.lock().unwrap() = true;
         let tx_clone = tx.clone();
         thread::spawn(move || {
-            thread::sleep(DAS_TIMEOUT);
+            thread::sleep(das);
             if *is_scanning.lock().unwrap() {
-                tx_clone.send(control).unwrap();
+                tx_clone.send(Event::Control(control)).unwrap();
             }
         });
     }
@@ -70,3 +195,122 @@ fn stop_das(is_scanning: &Arc<Mutex<bool>>) {
     let is_scanning = Arc::clone(is_scanning);
     *is_scanning.lock().unwrap() = false;
 }
+
+/// press taken as a tap, key is held from now on until its first repeat times out
+fn tap(
+    state: &mut Option<Held>,
+    tx: &Sender<Event>,
+    now: Instant,
+    control: ControlKind,
+    end: ControlKind,
+) -> Duration {
+    *state = Some(Held {
+        since: now,
+        last_press: now,
+        repeating: false,
+    });
+    tx.send(Event::Control(control)).unwrap();
+    if control == ControlKind::SoftDrop {
+        tx.send(Event::Control(end)).unwrap();
+    }
+    FIRST_REPEAT_TIMEOUT
+}
+
+/// handle press of left, right or soft drop when key releases aren't reported
+/// first press is a tap, key repeat means key is held down until repeating stops
+/// presses within `MIN_REPEAT_DELAY` of the last tap are taps too
+fn repeat_press(
+    tx: &Sender<Event>,
+    held: &Arc<Mutex<Option<Held>>>,
+    das: Duration,
+    control: ControlKind,
+) {
+    let (start, end) = match control {
+        ControlKind::Left => (ControlKind::LeftDasStart, ControlKind::LeftDasEnd),
+        ControlKind::Right => (ControlKind::RightDasStart, ControlKind::RightDasEnd),
+        _ => (ControlKind::SoftDrop, ControlKind::SoftDropEnd),
+    };
+    let now = Instant::now();
+    let mut state = held.lock().unwrap();
+    let is_tap = |held: &Held| !held.repeating && now - held.last_press < MIN_REPEAT_DELAY;
+    let timeout = match state.as_mut() {
+        Some(state) if !is_tap(state) => {
+            state.last_press = now;
+            if !state.repeating {
+                state.repeating = true;
+                // auto shift starts after DAS or first repeat, whichever comes later
+                let delay = if control == ControlKind::SoftDrop {
+                    Duration::ZERO
+                } else {
+                    das.saturating_sub(now - state.since)
+                };
+                let since = state.since;
+                let held = Arc::clone(held);
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    thread::sleep(delay);
+                    if held.lock().unwrap().is_some_and(|held| held.since == since) {
+                        tx.send(Event::Control(start)).unwrap();
+                    }
+                });
+            }
+            REPEAT_TIMEOUT
+        }
+        _ => tap(&mut state, tx, now, control, end),
+    };
+    let held = Arc::clone(held);
+    let tx = tx.clone();
+    thread::spawn(move || {
+        thread::sleep(timeout);
+        let mut state = held.lock().unwrap();
+        if state.is_some_and(|held| held.last_press == now) {
+            *state = None;
+            tx.send(Event::Control(end)).unwrap();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{self, Receiver};
+
+    use super::*;
+
+    const DAS: Duration = Duration::from_millis(100);
+
+    /// controls sent within `wait`
+    fn controls(rx: &Receiver<Event>, wait: Duration) -> Vec<ControlKind> {
+        thread::sleep(wait);
+        rx.try_iter()
+            .filter_map(|event| match event {
+                Event::Control(control) => Some(control),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fast_presses_are_taps() {
+        let (tx, rx) = mpsc::channel();
+        let held = Arc::new(Mutex::new(None));
+        repeat_press(&tx, &held, DAS, ControlKind::Left);
+        repeat_press(&tx, &held, DAS, ControlKind::Left);
+        assert_eq!(
+            controls(&rx, DAS * 2),
+            [ControlKind::Left, ControlKind::Left]
+        );
+    }
+
+    #[test]
+    fn late_press_is_key_repeat() {
+        let (tx, rx) = mpsc::channel();
+        let held = Arc::new(Mutex::new(None));
+        repeat_press(&tx, &held, DAS, ControlKind::Left);
+        thread::sleep(MIN_REPEAT_DELAY + Duration::from_millis(50));
+        repeat_press(&tx, &held, DAS, ControlKind::Left);
+        assert_eq!(
+            controls(&rx, Duration::from_millis(50)),
+            [ControlKind::Left, ControlKind::LeftDasStart]
+        );
+    }
+}
@@ -1,10 +1,21 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use rand::seq::SliceRandom;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    pieces::{self, Piece, PieceSet},
+    rotation::RotationSystem,
+    score::{self, Spin},
+};
 
 pub const GRID_WIDTH: u16 = 10;
 pub const GRID_HEIGHT: u16 = 24;
 
+pub const LOCK_DELAY: Duration = Duration::from_millis(500);
+/// longest preview, bag keeps at least this many pieces queued
+pub const MAX_PREVIEW: usize = 7;
+
 type Pos = (i8, i8);
 const TETRIMINO: usize = 4;
 
@@ -16,25 +27,8 @@ const S_CELLS: [Pos; TETRIMINO] = [(-1, 0), (0, 0), (0, 1), (1, 1)];
 const T_CELLS: [Pos; TETRIMINO] = [(-1, 0), (0, 0), (0, 1), (1, 0)];
 const Z_CELLS: [Pos; TETRIMINO] = [(-1, 1), (0, 1), (0, 0), (1, 0)];
 
-static JLSTZ_OFFSETS: [[Pos; 5]; 4] = [
-    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
-    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
-    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
-    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
-];
-static I_OFFSETS: [[Pos; 5]; 4] = [
-    [(0, 0), (-1, 0), (2, 0), (-1, 0), (2, 0)],
-    [(-1, 0), (0, 0), (0, 0), (0, 1), (0, -2)],
-    [(-1, 1), (1, 1), (-2, 1), (1, 0), (-2, 0)],
-    [(0, 1), (0, 1), (0, 1), (0, -1), (0, 2)],
-];
-static O_OFFSETS: [[Pos; 5]; 4] = [
-    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)], // No further offset data required
-    [(0, -1), (0, 0), (0, 0), (0, 0), (0, 0)],
-    [(-1, -1), (0, 0), (0, 0), (0, 0), (0, 0)],
-    [(-1, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
-];
-static Gravity: [f64; 20] = [
+/// seconds per row for each level
+static GRAVITY: [f64; 20] = [
     1.00000, // 1
     0.79300, // 2
     0.61780, // 3
@@ -59,7 +53,7 @@ static Gravity: [f64; 20] = [
 
 // TODO: rename to Up, Right, Down, Left
 // also use on das_shift(&self, dir: Direction, game: &Game)
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
     /// Zero : Initial state
     Z,
@@ -89,7 +83,7 @@ impl std::ops::AddAssign<Direction> for Direction {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MinoType {
     I,
     J,
@@ -98,10 +92,44 @@ pub enum MinoType {
     S,
     T,
     Z,
+    /// piece at the index of piece set, see `pieces`
+    Custom(u8),
 }
 
 impl MinoType {
-    pub fn get_cells(&self) -> &[Pos; TETRIMINO] {
+    pub const ALL: [MinoType; 7] = [
+        MinoType::I,
+        MinoType::J,
+        MinoType::L,
+        MinoType::O,
+        MinoType::S,
+        MinoType::T,
+        MinoType::Z,
+    ];
+
+    /// letter naming the piece, `Custom` has none
+    pub fn to_char(self) -> Option<char> {
+        match self {
+            MinoType::I => Some('I'),
+            MinoType::J => Some('J'),
+            MinoType::L => Some('L'),
+            MinoType::O => Some('O'),
+            MinoType::S => Some('S'),
+            MinoType::T => Some('T'),
+            MinoType::Z => Some('Z'),
+            MinoType::Custom(_) => None,
+        }
+    }
+
+    /// piece named by uppercase letter
+    pub fn from_char(c: char) -> Option<MinoType> {
+        MinoType::ALL
+            .into_iter()
+            .find(|mino_type| mino_type.to_char() == Some(c))
+    }
+
+    /// cells around Mino position, `pieces` has the cells of `Custom`
+    pub fn get_cells<'a>(&self, pieces: &'a [Piece]) -> &'a [Pos] {
         match self {
             MinoType::I => &I_CELLS,
             MinoType::J => &J_CELLS,
@@ -110,41 +138,51 @@ impl MinoType {
             MinoType::S => &S_CELLS,
             MinoType::T => &T_CELLS,
             MinoType::Z => &Z_CELLS,
+            MinoType::Custom(index) => &pieces[*index as usize].cells,
         }
     }
+
+    pub fn is_tetromino(&self) -> bool {
+        !matches!(self, MinoType::Custom(_))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Mino {
     pub mino_type: MinoType,
     pub direction: Direction,
+    /// decides how this Mino rotates
+    pub rotation: RotationSystem,
+    /// set `mino_type` comes from when it's `Custom`
+    pub pieces: PieceSet,
     pub ghost_y: i8,
     pub x: i8,
     pub y: i8,
 }
 
 impl Mino {
-    pub fn new(mino_type: MinoType, board: &Board) -> Mino {
-        let mut mino = Mino {
-            mino_type,
-            direction: Direction::Z,
-            ghost_y: 18,
-            x: 4,
-            y: 18,
-        };
+    pub fn new(
+        mino_type: MinoType,
+        rotation: RotationSystem,
+        pieces: &PieceSet,
+        board: &Board,
+    ) -> Mino {
+        let mut mino = rotation.spawn(mino_type, pieces);
         mino.update_ghost_y(board);
         return mino;
     }
 
-    pub fn get_cells(&self) -> [Pos; TETRIMINO] {
+    pub fn get_cells(&self) -> Vec<Pos> {
         self.mino_type
-            .get_cells()
-            .map(|(x, y)| match self.direction {
+            .get_cells(&self.pieces)
+            .iter()
+            .map(|&(x, y)| match self.direction {
                 Direction::Z => (self.x + x, self.y + y),
                 Direction::R => (self.x + y, self.y - x),
                 Direction::D => (self.x - x, self.y - y),
                 Direction::L => (self.x - y, self.y + x),
             })
+            .collect()
     }
 
     pub fn collides(&self, board: &Board) -> bool {
@@ -213,17 +251,10 @@ impl Mino {
     pub fn rotate(&mut self, direction: Direction, board: &Board) -> bool {
         let mut temp = self.clone();
         temp.direction += direction;
-        let offset_table = match self.mino_type {
-            MinoType::I => I_OFFSETS,
-            MinoType::O => O_OFFSETS,
-            _ => JLSTZ_OFFSETS,
-        };
-        let pre_offset = offset_table[self.direction as usize];
-        let post_offset = offset_table[temp.direction as usize];
-        for i in 0..5 {
+        for (x, y) in self.rotation.kicks(self, temp.direction, board) {
             let mut t = temp.clone();
-            t.x += pre_offset[i].0 - post_offset[i].0;
-            t.y += pre_offset[i].1 - post_offset[i].1;
+            t.x += x;
+            t.y += y;
             if !t.collides(board) {
                 *self = t;
                 self.update_ghost_y(board);
@@ -277,33 +308,207 @@ impl Board {
             false
         }
     }
+
+    /// push garbage row with hole at `hole` from the bottom
+    /// returns false when blocks were pushed out of the top
+    pub fn push_garbage(&mut self, hole: usize) -> bool {
+        let overflow = self.grid[GRID_HEIGHT as usize - 1]
+            .iter()
+            .any(|cell| !cell.is_empty());
+        self.grid.copy_within(0..GRID_HEIGHT as usize - 1, 1);
+        let mut row = [Cell::_Garbage; GRID_WIDTH as usize];
+        row[hole] = Cell::Empty;
+        self.grid[0] = row;
+        !overflow
+    }
+
+    /// rows without empty cells, from bottom to top
+    pub fn full_rows(&self) -> Vec<usize> {
+        (0..GRID_HEIGHT as usize)
+            .filter(|&row| self.grid[row].iter().all(|cell| !cell.is_empty()))
+            .collect()
+    }
+
+    /// remove rows and move rows above them down
+    /// `rows` should be sorted from bottom to top
+    pub fn remove_rows(&mut self, rows: &[usize]) {
+        let empty_line = [Cell::Empty; GRID_WIDTH as usize];
+        rows.iter().rev().for_each(|&row| {
+            for row in row..GRID_HEIGHT as usize - 1 {
+                self.grid[row] = self.grid[row + 1];
+            }
+            *self.grid.last_mut().unwrap() = empty_line;
+        });
+    }
+
+    /// number of rows containing garbage
+    pub fn garbage_rows(&self) -> usize {
+        self.grid
+            .iter()
+            .filter(|row| row.iter().any(|cell| matches!(cell, Cell::_Garbage)))
+            .count()
+    }
+}
+
+/// random hole for garbage row, different from previous hole
+pub fn random_hole(previous: Option<usize>) -> usize {
+    let mut rng = rand::thread_rng();
+    loop {
+        let hole = rng.gen_range(0..GRID_WIDTH as usize);
+        if Some(hole) != previous {
+            return hole;
+        }
+    }
 }
 
+#[derive(Debug, Clone)]
 pub struct Bag {
+    /// queued pieces, next one last
     pub list: Vec<MinoType>,
+    /// each bag has one of these
+    pieces: Vec<MinoType>,
+    /// kept in the bag so snapshots can replay the same pieces
+    rng: StdRng,
 }
 
-fn get_bag() -> [MinoType; 7] {
-    use MinoType::*;
-    let mut arr = [I, J, L, O, S, T, Z];
-    arr.shuffle(&mut rand::thread_rng());
+fn get_bag(rng: &mut StdRng, pieces: &[MinoType]) -> Vec<MinoType> {
+    let mut arr = pieces.to_vec();
+    arr.shuffle(rng);
     return arr;
 }
 
 impl Bag {
     pub fn new() -> Bag {
-        return Bag {
-            list: Vec::from(get_bag()),
+        Bag::with_pieces(MinoType::ALL.to_vec())
+    }
+
+    pub fn with_pieces(pieces: Vec<MinoType>) -> Bag {
+        let mut bag = Bag {
+            list: Vec::new(),
+            pieces,
+            rng: StdRng::from_entropy(),
         };
+        bag.fill();
+        bag
     }
+
     pub fn next(&mut self) -> MinoType {
-        if let Some(mino) = self.list.pop() {
-            return mino;
+        let mino = self.list.pop().unwrap();
+        self.fill();
+        mino
+    }
+
+    /// queue new bags behind the queued pieces until `MAX_PREVIEW` pieces are known
+    pub fn fill(&mut self) {
+        while self.list.len() < MAX_PREVIEW {
+            let mut list = get_bag(&mut self.rng, &self.pieces);
+            list.append(&mut self.list);
+            self.list = list;
+        }
+    }
+}
+
+/// things happened in game that modes may respond to
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// player got locked to board
+    Lock {
+        /// locked player
+        piece: Mino,
+        /// board before player was locked
+        board: Board,
+        cleared: u8,
+        spin: Spin,
+        perfect: bool,
+        /// score gained by this lock, including drop bonus
+        score: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    /// play time, only counted while game is running
+    pub elapsed: Duration,
+    pub pieces: u32,
+    pub lines: u32,
+    /// cleared lines which had garbage in them
+    pub garbage_cleared: u32,
+    /// total garbage lines sent, including canceled ones
+    pub attack: u32,
+    /// keys pressed to move, rotate, drop and hold pieces
+    pub keys: u32,
+}
+
+impl Stats {
+    /// pieces per second
+    pub fn pps(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.pieces as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// attack per minute
+    pub fn apm(&self) -> f64 {
+        let minutes = self.elapsed.as_secs_f64() / 60.0;
+        if minutes > 0.0 {
+            self.attack as f64 / minutes
         } else {
-            self.list.extend_from_slice(&get_bag());
-            return self.list.pop().unwrap();
+            0.0
         }
     }
+
+    /// keys per piece
+    pub fn kpp(&self) -> f64 {
+        if self.pieces > 0 {
+            self.keys as f64 / self.pieces as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// delays around placing a piece
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    /// entry delay (ARE), time between lock and next spawn
+    pub are: Duration,
+    /// added to entry delay when lines are cleared
+    pub line_clear_delay: Duration,
+    pub lock_delay: Duration,
+    /// time to fall one row, overrides level-based gravity when set
+    pub gravity: Option<Duration>,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing {
+            are: Duration::ZERO,
+            line_clear_delay: Duration::ZERO,
+            lock_delay: LOCK_DELAY,
+            gravity: None,
+        }
+    }
+}
+
+/// max number of placements kept for undo
+const HISTORY_LIMIT: usize = 1000;
+
+/// state of the game when a Mino spawned, including randomizer state
+#[derive(Debug, Clone)]
+struct Snapshot {
+    board: Board,
+    player: MinoType,
+    hold: Option<MinoType>,
+    can_hold: bool,
+    bags: Bag,
+    stats: Stats,
+    score: u64,
+    combo: u32,
+    back_to_back: bool,
+    pending_garbage: Vec<usize>,
 }
 
 pub struct Game {
@@ -323,14 +528,46 @@ pub struct Game {
     /// Some() when das is charging
     /// value is initial time when DAS charging is started
     pub das_charge_right: Option<Instant>,
+    pub stats: Stats,
+    pub score: u64,
+    /// starts from 1, decides gravity and score multiplier
+    pub level: u32,
+    /// number of consecutive line clears
+    pub combo: u32,
+    /// true when last line clear was tetris or T-Spin
+    pub back_to_back: bool,
+    /// true when last successful move was rotation
+    last_rotated: bool,
+    /// score from soft/hard drop of current player
+    drop_score: u64,
+    pub timing: Timing,
+    /// rotation system of spawned Minos
+    pub rotation: RotationSystem,
+    /// set of `MinoType::Custom` pieces in play
+    pub pieces: PieceSet,
+    /// Some() while waiting for next Mino to spawn
+    /// value is time left until spawn
+    spawn_delay: Option<Duration>,
+    /// holes of garbage rows waiting to rise
+    pub pending_garbage: Vec<usize>,
+    /// true when new Mino couldn't spawn
+    pub over: bool,
+    events: Vec<GameEvent>,
+    /// taken when current player spawned
+    spawn_snapshot: Option<Snapshot>,
+    /// snapshots of placed pieces, latest last
+    history: Vec<Snapshot>,
+    /// snapshots undone, latest undone last
+    redo_history: Vec<Snapshot>,
 }
 
 impl Game {
     pub fn new() -> Game {
         let mut bag = Bag::new();
         let board = Board::new();
-        let player = Mino::new(bag.next(), &board);
-        Game {
+        let pieces = PieceSet::default();
+        let player = Mino::new(bag.next(), RotationSystem::default(), &pieces, &board);
+        let mut game = Game {
             board: Board::new(),
             player,
             hold: None,
@@ -341,44 +578,372 @@ impl Game {
             canceled_drop: 0,
             das_charge_left: None,
             das_charge_right: None,
+            stats: Stats::default(),
+            score: 0,
+            level: 1,
+            combo: 0,
+            back_to_back: false,
+            last_rotated: false,
+            drop_score: 0,
+            timing: Timing::default(),
+            rotation: RotationSystem::default(),
+            pieces,
+            spawn_delay: None,
+            pending_garbage: Vec::new(),
+            over: false,
+            events: Vec::new(),
+            spawn_snapshot: None,
+            history: Vec::new(),
+            redo_history: Vec::new(),
+        };
+        game.spawn_snapshot = Some(game.snapshot());
+        game
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board,
+            player: self.player.mino_type,
+            hold: self.hold,
+            can_hold: self.can_hold,
+            bags: self.bags.clone(),
+            stats: self.stats,
+            score: self.score,
+            combo: self.combo,
+            back_to_back: self.back_to_back,
+            pending_garbage: self.pending_garbage.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.board = snapshot.board;
+        self.hold = snapshot.hold;
+        self.can_hold = snapshot.can_hold;
+        self.bags = snapshot.bags;
+        // play time and pressed keys keep going
+        self.stats = Stats {
+            elapsed: self.stats.elapsed,
+            keys: self.stats.keys,
+            ..snapshot.stats
+        };
+        self.score = snapshot.score;
+        self.combo = snapshot.combo;
+        self.back_to_back = snapshot.back_to_back;
+        self.pending_garbage = snapshot.pending_garbage;
+        self.spawn_delay = None;
+        self.over = false;
+        self.last_touch = None;
+        self.canceled_drop = 0;
+        self.drop_score = 0;
+        self.last_rotated = false;
+        self.spawn(snapshot.player);
+        self.spawn_snapshot = Some(self.snapshot());
+    }
+
+    /// undo last placement for good, so it can't be redone
+    pub fn take_back(&mut self) -> bool {
+        let undone = self.undo();
+        if undone {
+            self.redo_history.pop();
         }
+        undone
+    }
+
+    /// go back to when previous piece spawned
+    /// returns false when there's nothing to undo
+    pub fn undo(&mut self) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        match self.history.pop() {
+            Some(snapshot) => {
+                let current = self
+                    .spawn_snapshot
+                    .take()
+                    .unwrap_or_else(|| self.snapshot());
+                self.redo_history.push(current);
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// go forward to the placement undone last
+    /// returns false when there's nothing to redo
+    pub fn redo(&mut self) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        match self.redo_history.pop() {
+            Some(snapshot) => {
+                let current = self
+                    .spawn_snapshot
+                    .take()
+                    .unwrap_or_else(|| self.snapshot());
+                self.history.push(current);
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// start from given position, `queue` starts with the current piece
+    /// random pieces follow after `queue` runs out
+    pub fn load(&mut self, board: Board, queue: &[MinoType], hold: Option<MinoType>) {
+        self.board = board;
+        self.hold = hold;
+        self.can_hold = true;
+        match queue.split_first() {
+            Some((&current, next)) => {
+                self.bags.list = next.iter().rev().copied().collect();
+                self.bags.fill();
+                self.spawn(current);
+            }
+            None => self.spawn(self.player.mino_type),
+        }
+        self.history.clear();
+        self.redo_history.clear();
+        self.spawn_snapshot = Some(self.snapshot());
+    }
+
+    /// replace current player with new Mino of given type
+    pub fn set_player(&mut self, mino_type: MinoType) {
+        self.spawn(mino_type);
+        self.spawn_snapshot = Some(self.snapshot());
+    }
+
+    /// change rotation system, current player is spawned again
+    pub fn set_rotation(&mut self, rotation: RotationSystem) {
+        self.rotation = rotation;
+        self.set_player(self.player.mino_type);
+    }
+
+    /// play with given pieces instead of tetrominoes, bag starts over
+    pub fn set_pieces(&mut self, pieces: PieceSet) {
+        self.bags = Bag::with_pieces(pieces::mino_types(&pieces));
+        self.pieces = pieces;
+        let next = self.bags.next();
+        self.set_player(next);
+    }
+
+    pub fn set_hold(&mut self, hold: Option<MinoType>) {
+        self.hold = hold;
+        self.can_hold = true;
+        self.spawn_snapshot = Some(self.snapshot());
+    }
+
+    /// edit board cell by hand, cells under player can't be changed
+    pub fn set_cell(&mut self, x: i8, y: i8, cell: Cell) {
+        if !self.board.is_in_bounds(x, y) || self.player.get_cells().contains(&(x, y)) {
+            return;
+        }
+        self.board.grid[y as usize][x as usize] = cell;
+        self.player.update_ghost_y(&self.board);
+        self.spawn_snapshot = Some(self.snapshot());
     }
 
     /// merge player to board and set new player
     /// this will hard-drop Mino if Mino isn't at bottom
     pub fn lock_player(&mut self) {
+        if !self.is_active() {
+            return;
+        }
         self.player
             .shift(0, self.player.ghost_y - self.player.y, &self.board);
+        let board = self.board;
         self.player.get_cells().into_iter().for_each(|(x, y)| {
             self.board.grid[y as usize][x as usize] = Cell::Mino(self.player.mino_type);
         });
-        self.clear_lines();
-        self.player = Mino::new(self.bags.next(), &self.board);
+        if let Some(snapshot) = self.spawn_snapshot.take() {
+            if self.history.len() >= HISTORY_LIMIT {
+                self.history.remove(0);
+            }
+            self.history.push(snapshot);
+            self.redo_history.clear();
+        }
+        let spin = score::detect_spin(&self.player, &self.board, self.last_rotated);
+        let cleared = self.clear_lines();
+        let perfect = self.board.grid.iter().flatten().all(Cell::is_empty);
+        self.stats.pieces += 1;
+        self.stats.lines += cleared as u32;
+
+        if cleared > 0 {
+            self.combo += 1;
+        } else {
+            self.combo = 0;
+        }
+        let lock_score = score::lock_score(cleared, spin, self.back_to_back, self.combo, perfect)
+            * self.level as u64
+            + self.drop_score;
+        let attack = score::attack(cleared, spin, self.back_to_back, self.combo, perfect);
+        self.stats.attack += attack;
+        if cleared > 0 {
+            self.back_to_back = score::is_difficult(cleared, spin);
+            // attack cancels incoming garbage first
+            let canceled = (attack as usize).min(self.pending_garbage.len());
+            self.pending_garbage.drain(..canceled);
+        } else {
+            let holes = std::mem::take(&mut self.pending_garbage);
+            self.rise_garbage(&holes);
+        }
+        self.score += lock_score;
+        self.drop_score = 0;
+        self.last_rotated = false;
+        self.events.push(GameEvent::Lock {
+            piece: self.player.clone(),
+            board,
+            cleared,
+            spin,
+            perfect,
+            score: lock_score,
+        });
+        let delay = if cleared > 0 {
+            self.timing.are + self.timing.line_clear_delay
+        } else {
+            self.timing.are
+        };
+        if delay.is_zero() {
+            self.spawn_next();
+        } else {
+            self.spawn_delay = Some(delay);
+        }
         self.can_hold = true;
         self.last_touch = None;
         self.canceled_drop = 0;
     }
 
-    fn clear_lines(&mut self) {
-        let rows_to_clear: Vec<usize> = (0..GRID_HEIGHT as usize)
-            .filter(|&row| self.board.grid[row].iter().all(|cell| !cell.is_empty()))
-            .collect();
-        let empty_line = [Cell::Empty; GRID_WIDTH as usize];
-        rows_to_clear.into_iter().rev().for_each(|row| {
-            for row in row..GRID_HEIGHT as usize - 1 {
-                self.board.grid[row] = self.board.grid[row + 1];
+    /// false while waiting for next Mino to spawn
+    pub fn is_active(&self) -> bool {
+        self.spawn_delay.is_none()
+    }
+
+    /// progress entry delay, spawns next Mino when it's over
+    pub fn update_spawn_delay(&mut self, elapsed: Duration) {
+        if let Some(left) = self.spawn_delay {
+            if left <= elapsed {
+                self.spawn_delay = None;
+                self.spawn_next();
+            } else {
+                self.spawn_delay = Some(left - elapsed);
             }
-            *self.board.grid.last_mut().unwrap() = empty_line.clone();
-        });
+        }
+    }
+
+    fn spawn_next(&mut self) {
+        let next = self.bags.next();
+        self.spawn(next);
+        self.spawn_snapshot = Some(self.snapshot());
+    }
+
+    /// set new player, game is over when it can't be placed
+    fn spawn(&mut self, mino_type: MinoType) {
+        self.player = Mino::new(mino_type, self.rotation, &self.pieces, &self.board);
+        if self.player.collides(&self.board) {
+            self.over = true;
+        }
+    }
+
+    /// push garbage rows from the bottom, player is pushed up when overlapped
+    pub fn add_garbage(&mut self, holes: &[usize]) {
+        self.rise_garbage(holes);
+        while self.player.collides(&self.board) && self.player.y < GRID_HEIGHT as i8 {
+            self.player.y += 1;
+        }
+        if self.player.collides(&self.board) {
+            self.over = true;
+        }
+        self.player.update_ghost_y(&self.board);
+    }
+
+    /// queue garbage rows, they rise when next piece locks without clearing lines
+    pub fn queue_garbage(&mut self, holes: &[usize]) {
+        self.pending_garbage.extend_from_slice(holes);
+    }
+
+    /// push garbage rows to board without touching player
+    fn rise_garbage(&mut self, holes: &[usize]) {
+        for &hole in holes {
+            if !self.board.push_garbage(hole) {
+                self.over = true;
+            }
+        }
+    }
+
+    /// soft drop by one row, gives 1 point per row
+    pub fn soft_drop(&mut self) {
+        if self.shift(0, -1) {
+            self.drop_score += 1;
+        }
+    }
+
+    /// gives 2 points per row dropped
+    pub fn hard_drop(&mut self) {
+        if !self.is_active() {
+            return;
+        }
+        self.drop_score += 2 * (self.player.y - self.player.ghost_y) as u64;
+        self.lock_player();
+    }
+
+    /// time for player to fall one row on current level
+    pub fn drop_interval(&self) -> Duration {
+        if let Some(gravity) = self.timing.gravity {
+            return gravity;
+        }
+        let level = (self.level as usize).clamp(1, GRAVITY.len());
+        Duration::from_secs_f64(GRAVITY[level - 1])
+    }
+
+    /// move timers forward by time game was paused, so pause doesn't count as lock delay or DAS
+    pub fn delay_timers(&mut self, paused: Duration) {
+        for time in [
+            &mut self.last_touch,
+            &mut self.last_move,
+            &mut self.das_charge_left,
+            &mut self.das_charge_right,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *time += paused;
+        }
+    }
+
+    /// events happened since last call
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// returns number of cleared lines
+    fn clear_lines(&mut self) -> u8 {
+        let rows_to_clear = self.board.full_rows();
+        self.stats.garbage_cleared += rows_to_clear
+            .iter()
+            .filter(|&&row| {
+                self.board.grid[row]
+                    .iter()
+                    .any(|cell| matches!(cell, Cell::_Garbage))
+            })
+            .count() as u32;
+        self.board.remove_rows(&rows_to_clear);
+        rows_to_clear.len() as u8
+    }
+
+    /// true when player can be swapped with hold now
+    pub fn can_hold(&self) -> bool {
+        self.can_hold && self.is_active()
     }
 
     pub fn swap_hold(&mut self) {
-        if self.can_hold {
+        if self.can_hold() {
             let prev_type = self.player.mino_type;
             if let Some(hold) = self.hold {
-                self.player = Mino::new(hold, &self.board);
+                self.spawn(hold);
             } else {
-                self.player = Mino::new(self.bags.next(), &self.board);
+                let next = self.bags.next();
+                self.spawn(next);
             }
             self.hold = Some(prev_type);
             self.can_hold = false
@@ -386,18 +951,32 @@ impl Game {
     }
 
     // HACK: wait... two similar same name function for two separate structs?
-    pub fn shift(&mut self, x: i8, y: i8) {
+    /// returns true if player moved
+    pub fn shift(&mut self, x: i8, y: i8) -> bool {
+        if !self.is_active() {
+            return false;
+        }
         let last_line = self.player.y;
         let success = self.player.shift(x, y, &self.board);
         let moved_down = last_line > self.player.y;
+        if success {
+            self.last_rotated = false;
+        }
         self.move_reset(success, moved_down);
+        success
     }
 
     // FIX: auto lock isn't working for 15+ movements
     pub fn rotate(&mut self, direction: Direction) {
+        if !self.is_active() {
+            return;
+        }
         let last_line = self.player.y;
         let success = self.player.rotate(direction, &self.board);
         let moved_down = last_line > self.player.y;
+        if success {
+            self.last_rotated = true;
+        }
         self.move_reset(success, moved_down);
     }
 
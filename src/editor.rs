@@ -0,0 +1,157 @@
+//! Board editor, sets up a position to start playing from
+
+use std::sync::mpsc::Receiver;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+
+use crate::{
+    control::paint_button,
+    core::{Board, Cell, MinoType, GRID_HEIGHT, GRID_WIDTH},
+    fumen::Page,
+    tui::UI,
+    Event,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Board,
+    Queue,
+    Hold,
+}
+
+pub enum EditorAction {
+    Continue,
+    Start,
+    Quit,
+}
+
+pub struct Editor {
+    pub board: Board,
+    pub cursor: (i8, i8),
+    /// cell painted on board
+    pub brush: Cell,
+    /// first piece is the current piece
+    pub queue: Vec<MinoType>,
+    pub hold: Option<MinoType>,
+    pub focus: Focus,
+    /// pages of imported fumen
+    pub pages: Vec<Page>,
+    pub page: usize,
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        Editor {
+            board: Board::new(),
+            cursor: (0, 0),
+            brush: Cell::_Garbage,
+            queue: Vec::new(),
+            hold: None,
+            focus: Focus::Board,
+            pages: Vec::new(),
+            page: 0,
+        }
+    }
+
+    /// editor stepping through fumen pages
+    pub fn from_pages(pages: Vec<Page>) -> Editor {
+        let mut editor = Editor {
+            pages,
+            ..Editor::new()
+        };
+        editor.show_page(0);
+        editor
+    }
+
+    /// show board of the page with its piece, queue comes from quiz comment
+    fn show_page(&mut self, index: usize) {
+        let Some(page) = self.pages.get(index) else {
+            return;
+        };
+        self.page = index;
+        self.board = page.board_with_piece();
+        if let Some(quiz) = page.quiz() {
+            self.queue = quiz.current.into_iter().chain(quiz.next).collect();
+            self.hold = quiz.hold;
+        }
+    }
+
+    pub fn paint(&mut self, x: i8, y: i8, erase: bool) {
+        if self.board.is_in_bounds(x, y) {
+            let cell = if erase { Cell::Empty } else { self.brush };
+            self.board.grid[y as usize][x as usize] = cell;
+        }
+    }
+
+    fn move_cursor(&mut self, x: i8, y: i8) {
+        self.cursor.0 = (self.cursor.0 + x).clamp(0, GRID_WIDTH as i8 - 1);
+        self.cursor.1 = (self.cursor.1 + y).clamp(0, GRID_HEIGHT as i8 - 1);
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> EditorAction {
+        if key.kind == KeyEventKind::Release {
+            return EditorAction::Continue;
+        }
+        match key.code {
+            KeyCode::Enter => return EditorAction::Start,
+            KeyCode::Esc | KeyCode::Char('q') => return EditorAction::Quit,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Board => Focus::Queue,
+                    Focus::Queue => Focus::Hold,
+                    Focus::Hold => Focus::Board,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(1, 0),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(0, -1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(0, 1),
+            KeyCode::Char('c') => self.board = Board::new(),
+            KeyCode::Char('[') => self.show_page(self.page.saturating_sub(1)),
+            KeyCode::Char(']') => self.show_page(self.page + 1),
+            KeyCode::Char(' ') => self.paint(self.cursor.0, self.cursor.1, false),
+            KeyCode::Char('x') => self.paint(self.cursor.0, self.cursor.1, true),
+            KeyCode::Char('G') if self.focus == Focus::Board => self.brush = Cell::_Garbage,
+            KeyCode::Char(c) => {
+                if let Some(mino_type) = MinoType::from_char(c) {
+                    match self.focus {
+                        Focus::Board => self.brush = Cell::Mino(mino_type),
+                        Focus::Queue => self.queue.push(mino_type),
+                        Focus::Hold => self.hold = Some(mino_type),
+                    }
+                }
+            }
+            KeyCode::Backspace => match self.focus {
+                Focus::Board => self.paint(self.cursor.0, self.cursor.1, true),
+                Focus::Queue => {
+                    self.queue.pop();
+                }
+                Focus::Hold => self.hold = None,
+            },
+            _ => {}
+        }
+        EditorAction::Continue
+    }
+}
+
+/// run editor until player starts the game, None when player quit
+pub fn run(ui: &mut UI, rx: &Receiver<Event>, mut editor: Editor) -> Option<Editor> {
+    loop {
+        ui.render_editor(&editor).unwrap();
+        match rx.recv().ok()? {
+            Event::Key(key) => match editor.handle_key(key) {
+                EditorAction::Continue => {}
+                EditorAction::Start => return Some(editor),
+                EditorAction::Quit => return None,
+            },
+            Event::Mouse(mouse) => {
+                let cell = ui.board_cell(mouse.column, mouse.row);
+                if let (Some(erase), Some((x, y))) = (paint_button(&mouse), cell) {
+                    editor.cursor = (x, y);
+                    editor.paint(x, y, erase);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Finesse, inputs used for each piece compared with the minimum
+
+use crate::{
+    core::{Board, GameEvent, Mino},
+    movegen::finesse_path,
+    ControlKind,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Finesse {
+    /// inputs used for current piece
+    inputs: u32,
+    /// locked pieces which could be judged
+    pub pieces: u32,
+    /// pieces locked with more inputs than needed
+    pub faults: u32,
+}
+
+impl Finesse {
+    /// count control player used while piece is active
+    pub fn record(&mut self, control: ControlKind) {
+        use ControlKind::*;
+        match control {
+            Left | Right | Rotate | RotateCC | Rotate180 => self.inputs += 1,
+            // new piece starts after these
+            Hold | CyclePiece => self.reset(),
+            _ => {}
+        }
+    }
+
+    /// forget inputs used for current piece
+    pub fn reset(&mut self) {
+        self.inputs = 0;
+    }
+
+    /// minimum inputs moving spawned `piece` to where `target` lands
+    /// None when `target` needs soft drop
+    pub fn optimal_inputs(piece: &Mino, target: &Mino, board: &Board) -> Option<Vec<ControlKind>> {
+        let spawned = Mino::new(piece.mino_type, piece.rotation, &piece.pieces, board);
+        finesse_path(&spawned, target, board)
+    }
+
+    /// true when current piece used more inputs than `optimal`
+    pub fn is_fault(&self, optimal: &[ControlKind]) -> bool {
+        self.inputs > optimal.len() as u32
+    }
+
+    /// judge locked piece, placements needing soft drop are not judged
+    pub fn on_event(&mut self, event: &GameEvent) {
+        let GameEvent::Lock { piece, board, .. } = event;
+        if let Some(optimal) = Finesse::optimal_inputs(piece, piece, board) {
+            self.pieces += 1;
+            if self.is_fault(&optimal) {
+                self.faults += 1;
+            }
+        }
+        self.reset();
+    }
+
+    /// percentage of pieces placed without fault
+    pub fn percent(&self) -> f64 {
+        if self.pieces == 0 {
+            return 100.0;
+        }
+        (self.pieces - self.faults) as f64 / self.pieces as f64 * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{Direction, MinoType},
+        pieces::PieceSet,
+        rotation::RotationSystem,
+        score::Spin,
+    };
+
+    /// lock of T turned to `direction` and moved by `dx` on empty board
+    fn lock_t(direction: Direction, dx: i8) -> GameEvent {
+        let board = Board::new();
+        let mut piece = Mino::new(
+            MinoType::T,
+            RotationSystem::default(),
+            &PieceSet::default(),
+            &board,
+        );
+        piece.direction = direction;
+        piece.x += dx;
+        while piece.shift(0, -1, &board) {}
+        GameEvent::Lock {
+            piece,
+            board,
+            cleared: 0,
+            spin: Spin::None,
+            perfect: false,
+            score: 0,
+        }
+    }
+
+    #[test]
+    fn optimal_placement_is_no_fault() {
+        let mut finesse = Finesse::default();
+        // DAS sends Left before moving to the wall
+        finesse.record(ControlKind::Left);
+        finesse.record(ControlKind::LeftDasStart);
+        finesse.on_event(&lock_t(Direction::Z, -3));
+        assert_eq!((finesse.pieces, finesse.faults), (1, 0));
+        assert_eq!(finesse.percent(), 100.0);
+    }
+
+    #[test]
+    fn extra_input_is_fault() {
+        let mut finesse = Finesse::default();
+        // two cw rotations instead of one 180
+        finesse.record(ControlKind::Rotate);
+        finesse.record(ControlKind::Rotate);
+        finesse.on_event(&lock_t(Direction::D, 0));
+        assert_eq!((finesse.pieces, finesse.faults), (1, 1));
+
+        // inputs don't carry over to next piece
+        finesse.record(ControlKind::Rotate180);
+        finesse.on_event(&lock_t(Direction::D, 0));
+        assert_eq!((finesse.pieces, finesse.faults), (2, 1));
+        assert_eq!(finesse.percent(), 50.0);
+    }
+
+    #[test]
+    fn inputs_before_hold_are_not_counted() {
+        let mut finesse = Finesse::default();
+        finesse.record(ControlKind::Right);
+        finesse.record(ControlKind::Rotate);
+        finesse.record(ControlKind::Hold);
+        finesse.record(ControlKind::Left);
+        finesse.on_event(&lock_t(Direction::Z, -1));
+        assert_eq!((finesse.pieces, finesse.faults), (1, 0));
+    }
+}
@@ -0,0 +1,890 @@
+//! Fumen (v115) encoder and decoder
+//! https://knewjade.github.io/fumen-for-mobile/
+//!
+//! Fumen field is 23 rows high with one extra garbage row below the floor.
+//! Piece positions use the same rotation center as `Mino`.
+
+use crate::{
+    core::{Board, Cell, Direction, Game, Mino, MinoType, GRID_WIDTH},
+    pieces::PieceSet,
+    rotation::RotationSystem,
+};
+
+const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const COMMENT_TABLE: &[u8; 95] = b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const COMMENT_BASE: u32 = COMMENT_TABLE.len() as u32 + 1;
+const PREFIX: &str = "v115@";
+
+const FIELD_WIDTH: usize = GRID_WIDTH as usize;
+const FIELD_TOP: usize = 23;
+/// field rows with the garbage row
+const FIELD_BLOCKS: usize = (FIELD_TOP + 1) * FIELD_WIDTH;
+const GRAY: u8 = 8;
+/// field value meaning "same as previous page"
+const NO_CHANGE: u32 = 8 * FIELD_BLOCKS as u32 + FIELD_BLOCKS as u32 - 1;
+/// fumen inserts '?' every this many characters
+const CHUNK: usize = 47;
+
+/// one page of a fumen
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// board before `piece` is placed
+    pub board: Board,
+    pub piece: Option<Mino>,
+    pub comment: String,
+    /// when true, `piece` is locked and lines are cleared for next page
+    pub lock: bool,
+}
+
+/// queue given in fumen quiz comment, `#Q=[hold](current)next`
+#[derive(Debug, Clone, Default)]
+pub struct Quiz {
+    pub hold: Option<MinoType>,
+    pub current: Option<MinoType>,
+    pub next: Vec<MinoType>,
+}
+
+fn piece_code(mino_type: MinoType) -> u8 {
+    match mino_type {
+        MinoType::I => 1,
+        MinoType::L => 2,
+        MinoType::O => 3,
+        MinoType::Z => 4,
+        MinoType::T => 5,
+        MinoType::J => 6,
+        MinoType::S => 7,
+        MinoType::Custom(_) => GRAY,
+    }
+}
+
+fn piece_from_code(code: u8) -> Option<MinoType> {
+    match code {
+        1 => Some(MinoType::I),
+        2 => Some(MinoType::L),
+        3 => Some(MinoType::O),
+        4 => Some(MinoType::Z),
+        5 => Some(MinoType::T),
+        6 => Some(MinoType::J),
+        7 => Some(MinoType::S),
+        _ => None,
+    }
+}
+
+fn piece_char(mino_type: MinoType) -> char {
+    mino_type.to_char().unwrap_or('?')
+}
+
+fn rotation_code(direction: Direction) -> u32 {
+    match direction {
+        Direction::D => 0,
+        Direction::R => 1,
+        Direction::Z => 2,
+        Direction::L => 3,
+    }
+}
+
+fn rotation_from_code(code: u32) -> Direction {
+    match code {
+        0 => Direction::D,
+        1 => Direction::R,
+        2 => Direction::Z,
+        _ => Direction::L,
+    }
+}
+
+/// fumen stores some rotations of O, I, S and Z with a shifted center,
+/// so rotations covering same cells share one position
+/// returns fumen position minus `Mino` position
+fn position_correction(mino_type: MinoType, direction: Direction) -> (i8, i8) {
+    match (mino_type, direction) {
+        (MinoType::O, Direction::L) => (-1, 1),
+        (MinoType::O, Direction::D) => (-1, 0),
+        (MinoType::O, Direction::Z) => (0, 1),
+        (MinoType::I, Direction::D) => (-1, 0),
+        (MinoType::I, Direction::L) => (0, 1),
+        (MinoType::S, Direction::Z) => (0, 1),
+        (MinoType::S, Direction::R) => (1, 0),
+        (MinoType::Z, Direction::Z) => (0, 1),
+        (MinoType::Z, Direction::L) => (-1, 0),
+        _ => (0, 0),
+    }
+}
+
+/// fumen field, rows from top to bottom followed by the garbage row
+#[derive(Clone, Copy)]
+struct Field {
+    cells: [u8; FIELD_BLOCKS],
+}
+
+impl Field {
+    fn empty() -> Field {
+        Field {
+            cells: [0; FIELD_BLOCKS],
+        }
+    }
+
+    /// y is -1 for the garbage row
+    fn index(x: i8, y: i8) -> Option<usize> {
+        let row = FIELD_TOP as i8 - 1 - y;
+        if (0..FIELD_WIDTH as i8).contains(&x) && (0..=FIELD_TOP as i8).contains(&row) {
+            Some(row as usize * FIELD_WIDTH + x as usize)
+        } else {
+            None
+        }
+    }
+
+    fn from_board(board: &Board) -> Field {
+        let mut field = Field::empty();
+        for (y, row) in board.grid.iter().enumerate().take(FIELD_TOP) {
+            for (x, cell) in row.iter().enumerate() {
+                let index = Field::index(x as i8, y as i8).unwrap();
+                field.cells[index] = match cell {
+                    Cell::Mino(mino_type) => piece_code(*mino_type),
+                    Cell::_Garbage => GRAY,
+                    Cell::Ghost | Cell::Empty => 0,
+                };
+            }
+        }
+        field
+    }
+
+    fn to_board(self) -> Board {
+        let mut board = Board::new();
+        for (y, row) in board.grid.iter_mut().enumerate().take(FIELD_TOP) {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let code = self.cells[Field::index(x as i8, y as i8).unwrap()];
+                *cell = match code {
+                    0 => Cell::Empty,
+                    GRAY => Cell::_Garbage,
+                    code => Cell::Mino(piece_from_code(code).unwrap()),
+                };
+            }
+        }
+        board
+    }
+
+    /// field of the next page after locking `piece`
+    fn after_lock(&self, piece: Option<&Mino>, rise: bool, mirror: bool) -> Field {
+        let mut field = *self;
+        if let Some(piece) = piece {
+            for (x, y) in piece.get_cells() {
+                if let Some(index) = Field::index(x, y) {
+                    field.cells[index] = piece_code(piece.mino_type);
+                }
+            }
+        }
+        // clear filled rows, garbage row is never cleared
+        let rows: Vec<[u8; FIELD_WIDTH]> = field.cells[..FIELD_TOP * FIELD_WIDTH]
+            .chunks(FIELD_WIDTH)
+            .filter(|row| row.contains(&0))
+            .map(|row| row.try_into().unwrap())
+            .collect();
+        let mut cleared = Field::empty();
+        let start = FIELD_TOP - rows.len();
+        for (i, row) in rows.iter().enumerate() {
+            let offset = (start + i) * FIELD_WIDTH;
+            cleared.cells[offset..offset + FIELD_WIDTH].copy_from_slice(row);
+        }
+        cleared.cells[FIELD_TOP * FIELD_WIDTH..]
+            .copy_from_slice(&field.cells[FIELD_TOP * FIELD_WIDTH..]);
+        field = cleared;
+
+        if rise {
+            // garbage row goes up into the field
+            field.cells.copy_within(FIELD_WIDTH.., 0);
+            field.cells[FIELD_TOP * FIELD_WIDTH..].fill(0);
+        }
+        if mirror {
+            for row in field.cells[..FIELD_TOP * FIELD_WIDTH].chunks_mut(FIELD_WIDTH) {
+                row.reverse();
+            }
+        }
+        field
+    }
+}
+
+struct Writer {
+    data: String,
+}
+
+impl Writer {
+    fn poll(&mut self, count: usize, mut value: u32) {
+        for _ in 0..count {
+            self.data.push(TABLE[(value % 64) as usize] as char);
+            value /= 64;
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn poll(&mut self, count: usize) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let c = *self
+                .data
+                .get(self.position)
+                .ok_or("fumen data ended unexpectedly")?;
+            let digit = TABLE
+                .iter()
+                .position(|&t| t == c)
+                .ok_or_else(|| format!("invalid character in fumen: {}", c as char))?;
+            value += digit as u32 * 64u32.pow(i as u32);
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// same as JavaScript `escape()`
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) {
+            escaped.push(c);
+        } else if (c as u32) < 256 {
+            escaped.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                escaped.push_str(&format!("%u{:04X}", unit));
+            }
+        }
+    }
+    escaped
+}
+
+/// same as JavaScript `unescape()`
+fn unescape(text: &str) -> String {
+    let mut units = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let hex = |from: usize, len: usize| -> Option<u16> {
+            let digits: String = chars.get(from..from + len)?.iter().collect();
+            u16::from_str_radix(&digits, 16).ok()
+        };
+        if chars[i] == '%' {
+            if chars.get(i + 1) == Some(&'u') {
+                if let Some(unit) = hex(i + 2, 4) {
+                    units.push(unit);
+                    i += 6;
+                    continue;
+                }
+            } else if let Some(unit) = hex(i + 1, 2) {
+                units.push(unit);
+                i += 3;
+                continue;
+            }
+        }
+        let mut buffer = [0; 2];
+        units.extend_from_slice(chars[i].encode_utf16(&mut buffer));
+        i += 1;
+    }
+    String::from_utf16_lossy(&units)
+}
+
+/// decode fumen string into pages
+pub fn decode(fumen: &str) -> Result<Vec<Page>, String> {
+    let body = fumen
+        .trim()
+        .strip_prefix(PREFIX)
+        .ok_or("only v115 fumen is supported")?
+        .replace('?', "");
+    let mut reader = Reader {
+        data: body.as_bytes(),
+        position: 0,
+    };
+
+    let mut pages = Vec::new();
+    let mut previous = Field::empty();
+    let mut repeat = 0;
+    let mut comment = String::new();
+    while !reader.is_empty() {
+        let mut field = previous;
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut index = 0;
+            while index < FIELD_BLOCKS {
+                let value = reader.poll(2)?;
+                let diff = (value / FIELD_BLOCKS as u32) as i32 - 8;
+                let count = (value % FIELD_BLOCKS as u32) as usize + 1;
+                if value == NO_CHANGE && index == 0 {
+                    repeat = reader.poll(1)?;
+                }
+                if index + count > FIELD_BLOCKS {
+                    return Err("invalid fumen field".to_string());
+                }
+                for cell in &mut field.cells[index..index + count] {
+                    *cell = (*cell as i32 + diff)
+                        .try_into()
+                        .map_err(|_| "invalid fumen field")?;
+                }
+                index += count;
+            }
+        }
+
+        let mut action = reader.poll(3)?;
+        let code = (action % 8) as u8;
+        action /= 8;
+        let direction = rotation_from_code(action % 4);
+        action /= 4;
+        let location = (action % FIELD_BLOCKS as u32) as usize;
+        action /= FIELD_BLOCKS as u32;
+        let rise = action % 2 == 1;
+        action /= 2;
+        let mirror = action % 2 == 1;
+        action /= 2;
+        // colorize flag is only used by fumen viewers
+        action /= 2;
+        let has_comment = action % 2 == 1;
+        action /= 2;
+        let lock = action % 2 == 0;
+
+        if has_comment {
+            let length = reader.poll(2)? as usize;
+            let mut escaped = String::new();
+            while escaped.len() < length {
+                let mut value = reader.poll(5)?;
+                for _ in 0..4 {
+                    if escaped.len() >= length {
+                        break;
+                    }
+                    let c = COMMENT_TABLE
+                        .get((value % COMMENT_BASE) as usize)
+                        .ok_or("invalid fumen comment")?;
+                    escaped.push(*c as char);
+                    value /= COMMENT_BASE;
+                }
+            }
+            comment = unescape(&escaped);
+        }
+
+        let piece = piece_from_code(code).map(|mino_type| {
+            let (dx, dy) = position_correction(mino_type, direction);
+            let x = (location % FIELD_WIDTH) as i8 - dx;
+            let y = FIELD_TOP as i8 - 1 - (location / FIELD_WIDTH) as i8 - dy;
+            Mino {
+                mino_type,
+                direction,
+                rotation: RotationSystem::default(),
+                pieces: PieceSet::default(),
+                ghost_y: y,
+                x,
+                y,
+            }
+        });
+
+        previous = if lock {
+            field.after_lock(piece.as_ref(), rise, mirror)
+        } else {
+            field
+        };
+        pages.push(Page {
+            board: field.to_board(),
+            piece,
+            comment: comment.clone(),
+            lock,
+        });
+    }
+    if pages.is_empty() {
+        return Err("fumen has no pages".to_string());
+    }
+    Ok(pages)
+}
+
+/// encode pages into fumen string
+/// fails when blocks or pieces are above the 23 rows fumen has
+pub fn encode(pages: &[Page]) -> Result<String, String> {
+    let mut writer = Writer {
+        data: String::new(),
+    };
+    let mut previous = Field::empty();
+    let mut previous_comment = String::new();
+    // position of repeat counter of unchanged field
+    let mut repeat_position: Option<usize> = None;
+    for (i, page) in pages.iter().enumerate() {
+        let above_field = page.board.grid[FIELD_TOP..]
+            .iter()
+            .flatten()
+            .any(|cell| matches!(cell, Cell::Mino(_) | Cell::_Garbage));
+        if above_field {
+            return Err(format!(
+                "page {} has blocks above row {} of fumen",
+                i + 1,
+                FIELD_TOP
+            ));
+        }
+        let field = Field::from_board(&page.board);
+
+        let mut values = Vec::new();
+        let mut last_diff = field.cells[0] as i32 - previous.cells[0] as i32 + 8;
+        let mut count = 0;
+        for index in 1..FIELD_BLOCKS {
+            let diff = field.cells[index] as i32 - previous.cells[index] as i32 + 8;
+            if diff == last_diff {
+                count += 1;
+            } else {
+                values.push(last_diff as u32 * FIELD_BLOCKS as u32 + count);
+                last_diff = diff;
+                count = 0;
+            }
+        }
+        values.push(last_diff as u32 * FIELD_BLOCKS as u32 + count);
+
+        if values != [NO_CHANGE] {
+            values.iter().for_each(|&value| writer.poll(2, value));
+            repeat_position = None;
+        } else {
+            match repeat_position {
+                Some(position) if writer.data.as_bytes()[position] != TABLE[63] => {
+                    let digit = TABLE
+                        .iter()
+                        .position(|&t| t == writer.data.as_bytes()[position])
+                        .unwrap();
+                    let next = TABLE[digit + 1] as char;
+                    writer
+                        .data
+                        .replace_range(position..position + 1, &next.to_string());
+                }
+                _ => {
+                    writer.poll(2, NO_CHANGE);
+                    repeat_position = Some(writer.data.len());
+                    writer.poll(1, 0);
+                }
+            }
+        }
+
+        let has_comment = page.comment != previous_comment;
+        let (code, rotation, location) = match &page.piece {
+            Some(piece) => {
+                let (dx, dy) = position_correction(piece.mino_type, piece.direction);
+                let in_field = piece
+                    .get_cells()
+                    .into_iter()
+                    .all(|(x, y)| y >= 0 && Field::index(x, y).is_some());
+                let location = Field::index(piece.x + dx, piece.y + dy)
+                    .filter(|_| in_field)
+                    .ok_or_else(|| format!("piece of page {} is outside of fumen", i + 1))?;
+                (
+                    piece_code(piece.mino_type) as u32,
+                    rotation_code(piece.direction),
+                    location as u32,
+                )
+            }
+            None => (0, 0, 0),
+        };
+        let mut action = !page.lock as u32;
+        action = action * 2 + has_comment as u32;
+        // colorize first page
+        action = action * 2 + (i == 0) as u32;
+        // mirror and rise are never used
+        action *= 4;
+        action = action * FIELD_BLOCKS as u32 + location;
+        action = action * 4 + rotation;
+        action = action * 8 + code;
+        writer.poll(3, action);
+
+        if has_comment {
+            let escaped: Vec<u8> = escape(&page.comment).bytes().take(4095).collect();
+            writer.poll(2, escaped.len() as u32);
+            for chunk in escaped.chunks(4) {
+                let value = chunk.iter().rev().fold(0, |value, &c| {
+                    let index = COMMENT_TABLE.iter().position(|&t| t == c).unwrap_or(0);
+                    value * COMMENT_BASE + index as u32
+                });
+                writer.poll(5, value);
+            }
+            previous_comment = page.comment.clone();
+        }
+
+        previous = if page.lock {
+            field.after_lock(page.piece.as_ref(), false, false)
+        } else {
+            field
+        };
+    }
+
+    let chunks: Vec<String> = writer
+        .data
+        .as_bytes()
+        .chunks(CHUNK)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect();
+    Ok(format!("{}{}", PREFIX, chunks.join("?")))
+}
+
+impl Page {
+    /// queue of the page, when comment is a quiz
+    pub fn quiz(&self) -> Option<Quiz> {
+        Quiz::parse(&self.comment)
+    }
+
+    /// board with the piece of the page drawn on it
+    pub fn board_with_piece(&self) -> Board {
+        let mut board = self.board;
+        if let Some(piece) = &self.piece {
+            for (x, y) in piece.get_cells() {
+                if board.is_in_bounds(x, y) {
+                    board.grid[y as usize][x as usize] = Cell::Mino(piece.mino_type);
+                }
+            }
+        }
+        board
+    }
+}
+
+/// page placing `piece` on `board`, for replays
+/// pieces other than tetrominoes are drawn on board as gray cells
+pub fn placement_page(piece: &Mino, board: &Board) -> Page {
+    let page = Page {
+        board: *board,
+        piece: Some(piece.clone()),
+        comment: String::new(),
+        lock: true,
+    };
+    if piece.mino_type.is_tetromino() {
+        page
+    } else {
+        Page {
+            board: page.board_with_piece(),
+            piece: None,
+            ..page
+        }
+    }
+}
+
+/// fumen of current position, queue is written as quiz comment
+/// pieces other than tetrominoes are written as gray cells without queue
+pub fn from_game(game: &Game) -> Result<String, String> {
+    let quiz = Quiz {
+        hold: game.hold,
+        current: Some(game.player.mino_type),
+        next: game.bags.list.iter().rev().copied().collect(),
+    };
+    let tetrominoes = quiz
+        .hold
+        .iter()
+        .chain(&quiz.current)
+        .chain(&quiz.next)
+        .all(MinoType::is_tetromino);
+    encode(&[Page {
+        board: game.board,
+        piece: (game.is_active() && tetrominoes).then(|| game.player.clone()),
+        comment: if tetrominoes {
+            quiz.to_comment()
+        } else {
+            String::new()
+        },
+        lock: true,
+    }])
+}
+
+impl Quiz {
+    /// parse `#Q=[hold](current)next` comment
+    pub fn parse(comment: &str) -> Option<Quiz> {
+        let rest = comment.strip_prefix("#Q=")?;
+        let (hold, rest) = rest.strip_prefix('[')?.split_once(']')?;
+        let (current, next) = rest.strip_prefix('(')?.split_once(')')?;
+        let next = next
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != ';')
+            .map(MinoType::from_char)
+            .collect::<Option<Vec<_>>>()?;
+        Some(Quiz {
+            hold: hold.chars().next().and_then(MinoType::from_char),
+            current: current.chars().next().and_then(MinoType::from_char),
+            next,
+        })
+    }
+
+    pub fn to_comment(&self) -> String {
+        let hold: String = self.hold.map(piece_char).into_iter().collect();
+        let current: String = self.current.map(piece_char).into_iter().collect();
+        let next: String = self.next.iter().copied().map(piece_char).collect();
+        format!("#Q=[{}]({}){}", hold, current, next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(board: Board, piece: Option<Mino>, comment: &str) -> Page {
+        Page {
+            board,
+            piece,
+            comment: comment.to_string(),
+            lock: true,
+        }
+    }
+
+    fn mino(mino_type: MinoType, direction: Direction, x: i8, y: i8) -> Mino {
+        let mut mino = Mino::new(
+            mino_type,
+            RotationSystem::default(),
+            &PieceSet::default(),
+            &Board::new(),
+        );
+        mino.direction = direction;
+        mino.x = x;
+        mino.y = y;
+        mino
+    }
+
+    /// boards are compared as fields, since Cell isn't comparable
+    fn garbage_board() -> Board {
+        let mut board = Board::new();
+        board.push_garbage(3);
+        board.push_garbage(7);
+        board
+    }
+
+    #[test]
+    fn empty_field() {
+        assert_eq!(
+            encode(&[page(Board::new(), None, "")]).unwrap(),
+            "v115@vhAAgH"
+        );
+        let pages = decode("v115@vhAAgH").unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].piece.is_none());
+    }
+
+    #[test]
+    fn round_trip_every_piece_and_rotation() {
+        for mino_type in MinoType::ALL {
+            for direction in [Direction::Z, Direction::R, Direction::D, Direction::L] {
+                let piece = mino(mino_type, direction, 4, 10);
+                let fumen = encode(&[page(garbage_board(), Some(piece.clone()), "")]).unwrap();
+                let pages = decode(&fumen).unwrap();
+                let decoded = pages[0].piece.as_ref().unwrap();
+                assert_eq!(
+                    (decoded.mino_type, decoded.direction, decoded.x, decoded.y),
+                    (piece.mino_type, piece.direction, piece.x, piece.y),
+                    "{}",
+                    fumen
+                );
+                assert_eq!(
+                    Field::from_board(&pages[0].board).cells,
+                    Field::from_board(&garbage_board()).cells
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_pages_with_comments() {
+        let pieces = [
+            mino(MinoType::I, Direction::Z, 4, 0),
+            mino(MinoType::T, Direction::D, 1, 2),
+            mino(MinoType::O, Direction::Z, 8, 1),
+        ];
+        let mut pages = Vec::new();
+        let mut field = Field::from_board(&garbage_board());
+        for (i, piece) in pieces.iter().enumerate() {
+            pages.push(page(
+                field.to_board(),
+                Some(piece.clone()),
+                &format!("#{}", i),
+            ));
+            field = field.after_lock(Some(piece), false, false);
+        }
+        let decoded = decode(&encode(&pages).unwrap()).unwrap();
+        assert_eq!(decoded.len(), pages.len());
+        for (decoded, page) in decoded.iter().zip(&pages) {
+            let piece = decoded.piece.as_ref().unwrap();
+            let expected = page.piece.as_ref().unwrap();
+            assert_eq!(
+                (piece.mino_type, piece.direction, piece.x, piece.y),
+                (
+                    expected.mino_type,
+                    expected.direction,
+                    expected.x,
+                    expected.y
+                )
+            );
+            assert_eq!(decoded.comment, page.comment);
+            assert_eq!(
+                Field::from_board(&decoded.board).cells,
+                Field::from_board(&page.board).cells
+            );
+        }
+    }
+
+    fn sorted_cells(piece: &Mino) -> Vec<(i8, i8)> {
+        let mut cells = piece.get_cells();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn decodes_shifted_rotations() {
+        // pieces at bottom left of empty field, rotations covering same
+        // cells are stored at same position
+        let cases = [
+            (
+                "vhAxOJ",
+                MinoType::I,
+                Direction::Z,
+                [(0, 0), (1, 0), (2, 0), (3, 0)],
+            ),
+            (
+                "vhAJEJ",
+                MinoType::I,
+                Direction::R,
+                [(0, 0), (0, 1), (0, 2), (0, 3)],
+            ),
+            (
+                "vhAhOJ",
+                MinoType::I,
+                Direction::D,
+                [(0, 0), (1, 0), (2, 0), (3, 0)],
+            ),
+            (
+                "vhAZEJ",
+                MinoType::I,
+                Direction::L,
+                [(0, 0), (0, 1), (0, 2), (0, 3)],
+            ),
+            (
+                "vhATJJ",
+                MinoType::O,
+                Direction::Z,
+                [(0, 0), (0, 1), (1, 0), (1, 1)],
+            ),
+            (
+                "vhALJJ",
+                MinoType::O,
+                Direction::R,
+                [(0, 0), (0, 1), (1, 0), (1, 1)],
+            ),
+            (
+                "vhADJJ",
+                MinoType::O,
+                Direction::D,
+                [(0, 0), (0, 1), (1, 0), (1, 1)],
+            ),
+            (
+                "vhAbJJ",
+                MinoType::O,
+                Direction::L,
+                [(0, 0), (0, 1), (1, 0), (1, 1)],
+            ),
+            (
+                "vhA3JJ",
+                MinoType::S,
+                Direction::Z,
+                [(0, 0), (1, 0), (1, 1), (2, 1)],
+            ),
+            (
+                "vhAvJJ",
+                MinoType::S,
+                Direction::R,
+                [(0, 1), (0, 2), (1, 0), (1, 1)],
+            ),
+            (
+                "vhAnJJ",
+                MinoType::S,
+                Direction::D,
+                [(0, 0), (1, 0), (1, 1), (2, 1)],
+            ),
+            (
+                "vhA/JJ",
+                MinoType::S,
+                Direction::L,
+                [(0, 1), (0, 2), (1, 0), (1, 1)],
+            ),
+            (
+                "vhA0JJ",
+                MinoType::Z,
+                Direction::Z,
+                [(0, 1), (1, 0), (1, 1), (2, 0)],
+            ),
+            (
+                "vhAMJJ",
+                MinoType::Z,
+                Direction::R,
+                [(0, 0), (0, 1), (1, 1), (1, 2)],
+            ),
+            (
+                "vhAkJJ",
+                MinoType::Z,
+                Direction::D,
+                [(0, 1), (1, 0), (1, 1), (2, 0)],
+            ),
+            (
+                "vhAcJJ",
+                MinoType::Z,
+                Direction::L,
+                [(0, 0), (0, 1), (1, 1), (1, 2)],
+            ),
+        ];
+        for (data, mino_type, direction, cells) in cases {
+            let fumen = format!("{}{}", PREFIX, data);
+            let pages = decode(&fumen).unwrap();
+            let piece = pages[0].piece.as_ref().unwrap();
+            assert_eq!((piece.mino_type, piece.direction), (mino_type, direction));
+            assert_eq!(sorted_cells(piece), cells, "{}", data);
+            assert_eq!(encode(&pages).unwrap(), fumen);
+        }
+    }
+
+    #[test]
+    fn decodes_rise_and_unlocked_piece() {
+        // garbage row with hole at left rises after I locks,
+        // T on second page isn't locked so third page has same field
+        let pages = decode("v115@mhI8xILvhB1pfAAA").unwrap();
+        assert_eq!(pages.len(), 3);
+        assert!(pages[0].lock && !pages[1].lock);
+        assert!(pages[0]
+            .board
+            .grid
+            .iter()
+            .flatten()
+            .all(|cell| matches!(cell, Cell::Empty)));
+        let mut expected = Board::new();
+        for x in 1..FIELD_WIDTH {
+            expected.grid[0][x] = Cell::_Garbage;
+        }
+        for x in 4..8 {
+            expected.grid[1][x] = Cell::Mino(MinoType::I);
+        }
+        let expected = Field::from_board(&expected).cells;
+        assert_eq!(Field::from_board(&pages[1].board).cells, expected);
+        assert_eq!(Field::from_board(&pages[2].board).cells, expected);
+        let piece = pages[1].piece.as_ref().unwrap();
+        assert_eq!(sorted_cells(piece), [(0, 1), (1, 1), (1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn decodes_quiz_comment() {
+        let pages = decode("v115@vhAAgWYAFLDmClcJSAVDEHBEooRBUoAVBJ3jFD").unwrap();
+        assert_eq!(pages[0].comment, "#Q=[](T)IOSZ");
+        let quiz = pages[0].quiz().unwrap();
+        assert_eq!(quiz.hold, None);
+        assert_eq!(quiz.current, Some(MinoType::T));
+        assert_eq!(
+            quiz.next,
+            [MinoType::I, MinoType::O, MinoType::S, MinoType::Z]
+        );
+    }
+
+    #[test]
+    fn rejects_blocks_above_field() {
+        let piece = mino(MinoType::I, Direction::R, 4, 22);
+        assert!(encode(&[page(Board::new(), Some(piece), "")]).is_err());
+        let mut board = Board::new();
+        board.grid[FIELD_TOP][0] = Cell::_Garbage;
+        assert!(encode(&[page(board, None, "")]).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! Keys bound to controls, and the screen to rebind them
+
+use std::{fmt, sync::mpsc::Receiver};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::{tui::UI, ControlKind, Event};
+
+pub const PRESET_NAMES: [&str; 3] = ["vim", "arrows", "wasd"];
+
+/// controls which can be bound, with their names in config
+pub const ACTIONS: [(ControlKind, &str); 18] = [
+    (ControlKind::Left, "left"),
+    (ControlKind::Right, "right"),
+    (ControlKind::SoftDrop, "soft_drop"),
+    (ControlKind::HardDrop, "hard_drop"),
+    (ControlKind::RotateCC, "rotate_ccw"),
+    (ControlKind::Rotate, "rotate_cw"),
+    (ControlKind::Rotate180, "rotate_180"),
+    (ControlKind::Hold, "hold"),
+    (ControlKind::Undo, "undo"),
+    (ControlKind::Redo, "redo"),
+    (ControlKind::CyclePiece, "cycle_piece"),
+    (ControlKind::CycleHold, "cycle_hold"),
+    (ControlKind::Export, "export"),
+    (ControlKind::Solve, "solve"),
+    (ControlKind::ReloadConfig, "reload_config"),
+    (ControlKind::Pause, "pause"),
+    (ControlKind::Restart, "restart"),
+    (ControlKind::Quit, "quit"),
+];
+
+/// controls other than moving and rotating, same in every preset
+const COMMON_KEYS: [&[&str]; 10] = [
+    &["u"],
+    &["r"],
+    &["n"],
+    &["m"],
+    &["f"],
+    &["v"],
+    &["f5"],
+    &["p", "esc"],
+    &["ctrl+r"],
+    &["q"],
+];
+
+/// key code with modifiers, shift is part of the character for letters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn from_event(key: &KeyEvent) -> Key {
+        let mut modifiers =
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if let KeyCode::Char(_) = key.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Key {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    /// key written like `h`, `space`, `left`, `f5` or `ctrl+z`
+    pub fn parse(text: &str) -> Option<Key> {
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // `+` itself is a key too
+        if text.ends_with("++") || text == "+" {
+            parts.pop();
+            *parts.last_mut()? = "+";
+        }
+        let name = parts.pop()?;
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+        }
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "esc" => KeyCode::Esc,
+                name => KeyCode::F(name.strip_prefix('f')?.parse().ok().filter(|n| *n > 0)?),
+            },
+        };
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        Some(Key::from_event(&KeyEvent::new(code, modifiers)))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+/// keys of each action in `ACTIONS`, a key is bound to one action at most
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<Vec<Key>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset(PRESET_NAMES[0]).unwrap()
+    }
+}
+
+impl Keymap {
+    pub fn preset(name: &str) -> Option<Keymap> {
+        let keys: [&[&str]; 8] = match name.to_lowercase().as_str() {
+            "vim" => [
+                &["h"],
+                &["l"],
+                &["j"],
+                &["space"],
+                &["a"],
+                &["d"],
+                &["s"],
+                &["y"],
+            ],
+            "arrows" => [
+                &["left"],
+                &["right"],
+                &["down"],
+                &["space"],
+                &["z"],
+                &["up", "x"],
+                &["a"],
+                &["c"],
+            ],
+            "wasd" => [
+                &["a"],
+                &["d"],
+                &["s"],
+                &["w"],
+                &["j"],
+                &["l"],
+                &["k"],
+                &["space"],
+            ],
+            _ => return None,
+        };
+        let bindings = keys
+            .into_iter()
+            .chain(COMMON_KEYS)
+            .map(|keys| keys.iter().map(|key| Key::parse(key).unwrap()).collect())
+            .collect();
+        Some(Keymap { bindings })
+    }
+
+    /// keys of action at `index` of `ACTIONS`
+    pub fn keys(&self, index: usize) -> &[Key] {
+        &self.bindings[index]
+    }
+
+    /// keys bound to `control`
+    pub fn keys_of(&self, control: ControlKind) -> &[Key] {
+        match ACTIONS.iter().position(|(kind, _)| *kind == control) {
+            Some(index) => self.keys(index),
+            None => &[],
+        }
+    }
+
+    /// add key to action, taking it from the action it was bound to
+    pub fn bind(&mut self, index: usize, key: Key) {
+        self.bindings
+            .iter_mut()
+            .for_each(|keys| keys.retain(|k| *k != key));
+        self.bindings[index].push(key);
+    }
+
+    pub fn clear(&mut self, index: usize) {
+        self.bindings[index].clear();
+    }
+
+    pub fn control(&self, key: &KeyEvent) -> Option<ControlKind> {
+        let key = Key::from_event(key);
+        let index = self.bindings.iter().position(|keys| keys.contains(&key))?;
+        Some(ACTIONS[index].0)
+    }
+}
+
+pub enum RebindAction {
+    Continue,
+    Save,
+    Cancel,
+}
+
+pub struct Rebind {
+    pub keymap: Keymap,
+    /// selected row of `ACTIONS`
+    pub cursor: usize,
+    /// next key pressed is bound to selected action
+    pub capturing: bool,
+    /// preset last applied with tab
+    pub preset: Option<usize>,
+}
+
+impl Rebind {
+    pub fn new(keymap: Keymap) -> Rebind {
+        Rebind {
+            keymap,
+            cursor: 0,
+            capturing: false,
+            preset: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> RebindAction {
+        if key.kind == KeyEventKind::Release {
+            return RebindAction::Continue;
+        }
+        if self.capturing {
+            // keys which can't be written in config aren't bound
+            let key = Key::from_event(&key);
+            if key.code != KeyCode::Esc && Key::parse(&key.to_string()) == Some(key) {
+                self.keymap.bind(self.cursor, key);
+            }
+            self.capturing = false;
+            return RebindAction::Continue;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(ACTIONS.len() - 1)
+            }
+            KeyCode::Enter => self.capturing = true,
+            KeyCode::Backspace | KeyCode::Delete => self.keymap.clear(self.cursor),
+            KeyCode::Tab => {
+                let preset = self
+                    .preset
+                    .map_or(0, |preset| (preset + 1) % PRESET_NAMES.len());
+                self.keymap = Keymap::preset(PRESET_NAMES[preset]).unwrap();
+                self.preset = Some(preset);
+            }
+            KeyCode::Char('w') => return RebindAction::Save,
+            KeyCode::Esc | KeyCode::Char('q') => return RebindAction::Cancel,
+            _ => {}
+        }
+        RebindAction::Continue
+    }
+}
+
+/// run rebind screen, returns the new keymap when player saved it
+pub fn run(ui: &mut UI, rx: &Receiver<Event>, keymap: Keymap) -> Option<Keymap> {
+    let mut rebind = Rebind::new(keymap);
+    loop {
+        ui.render_rebind(&rebind).unwrap();
+        if let Event::Key(key) = rx.recv().ok()? {
+            match rebind.handle_key(key) {
+                RebindAction::Continue => {}
+                RebindAction::Save => return Some(rebind.keymap),
+                RebindAction::Cancel => return None,
+            }
+        }
+    }
+}
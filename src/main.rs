@@ -1,24 +1,59 @@
+mod config;
 mod control;
 mod core;
+mod editor;
+mod finesse;
+mod fumen;
+mod keymap;
+mod menu;
+mod mode;
+mod movegen;
+mod pieces;
+mod records;
+mod rotation;
+mod score;
+mod solver;
+mod tbp;
 mod tui;
 
 use std::{
-    sync::mpsc,
+    env,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
     thread,
     time::{Duration, Instant},
 };
 
-use control::handle_controls;
+use control::{handle_controls, paint_button};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
 
-use crate::core::{Direction, Game};
+use crate::{
+    config::Config,
+    core::{Board, Direction, Game, GameEvent, MinoType},
+    menu::{Menu, MenuAction},
+    mode::{Mode, Options},
+    pieces::PieceSet,
+    records::{Records, Replay, Replays},
+};
 
 pub enum Event {
     Control(ControlKind),
+    Key(KeyEvent),
+    Mouse(MouseEvent),
     Tick,
     ArrTick,
     FixTimeout,
+    /// process is about to be stopped, game pauses and answers when done drawing
+    Suspend(Sender<()>),
+    /// process continued after being stopped
+    Resumed,
+    /// terminal lost focus, held keys were released before it
+    FocusLost,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ControlKind {
     Quit,
 
@@ -31,6 +66,7 @@ pub enum ControlKind {
     RightDasEnd,
 
     SoftDrop,
+    SoftDropEnd,
 
     Rotate,
     RotateCC,
@@ -38,139 +74,722 @@ pub enum ControlKind {
 
     HardDrop,
     Hold,
+
+    Undo,
+    Redo,
+    CyclePiece,
+    CycleHold,
+
+    Export,
+    Solve,
+    ReloadConfig,
+
+    Pause,
+    Restart,
 }
 
-const LOCK_DELAY: Duration = Duration::from_millis(500);
-const ARR_TIMEOUT: Duration = Duration::from_millis(5);
-const DAS_TIMEOUT: Duration = Duration::from_millis(122);
+impl ControlKind {
+    /// control used to play a piece, counted for keys per piece
+    fn is_piece_input(self) -> bool {
+        use ControlKind::*;
+        matches!(
+            self,
+            Left | Right | SoftDrop | Rotate | RotateCC | Rotate180 | HardDrop | Hold
+        )
+    }
+}
 
 const FPS: u64 = 60;
 const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / FPS);
-const GRAVITY_FRAMES: u8 = 60;
 
-fn main() {
-    let mut ui = tui::UI::new().expect("Can't initialize TUI");
-    let mut game = Game::new();
+const USAGE: &str =
+    "usage: tet-rs [--mode MODE] [--goal GOAL] [--minutes MINUTES] [--target LINES] [--rows ROWS] [--opener NAME] [--bag fixed|random] [--rotation srs|srs+|ars|nrs|none] [--kicks-180 srs-x|nullpomino] [--pieces SET] [--keys] [--edit] [--fumen FUMEN] [--pc-height LINES] [--pc-all] [--bot COMMAND...]";
 
-    // control thread
-    let (tx, rx) = mpsc::channel::<ControlKind>();
-    thread::spawn(move || handle_controls(tx));
+/// position game starts from, instead of empty board
+struct Start {
+    board: Board,
+    queue: Vec<MinoType>,
+    hold: Option<MinoType>,
+}
 
-    let game_start_time = Instant::now();
-    let mut previous_frame_time = game_start_time;
-    let mut lag = Duration::from_secs(0);
+/// how a game stopped
+enum Ending {
+    /// game over or mode finished
+    Finished,
+    Quit,
+    Restart,
+}
 
-    let mut previous_arr_time = game_start_time;
-    let mut gravity_frame_count = 0;
+/// state kept across games, from command line and config
+struct Session<'a> {
+    options: Options<'a>,
+    ui: tui::UI,
+    rx: Receiver<Event>,
+    config: Config,
+    /// config read by control thread
+    shared_config: Arc<Mutex<Config>>,
+    /// set given as `--pieces`
+    pieces: Option<PieceSet>,
+    solver_settings: solver::Settings,
+    bot: Option<tbp::Bot>,
+    /// fumens exported while playing, printed after exit
+    exported: Vec<String>,
+    /// errors printed after exit
+    errors: Vec<String>,
+    lag_frame_count: u32,
+}
 
-    let mut lag_frame_count = 0;
+fn main() {
+    // `--bot <command> [args...]` lets TBP bot play the game
+    let args: Vec<String> = env::args().skip(1).collect();
+    let bot_index = args.iter().position(|arg| arg == "--bot");
+    let options = match Options::parse(&args[..bot_index.unwrap_or(args.len())]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = mode::from_options(&options) {
+        eprintln!("{}\n{}", e, USAGE);
+        std::process::exit(2);
+    }
+    let pages = match options.get_str("fumen").map(fumen::decode).transpose() {
+        Ok(pages) => pages,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let solver_settings = match solver::Settings::from_options(&options) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = rotation::RotationSystem::from_options(&options, &config.rules) {
+        eprintln!("{}\n{}", e, USAGE);
+        std::process::exit(2);
+    }
+    let pieces = match pieces::from_options(&options) {
+        Ok(pieces) => pieces,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let piece_set = pieces.as_ref().unwrap_or(&config.rules.piece_set);
+    if bot_index.is_some() && !piece_set.is_empty() {
+        eprintln!("--bot can only play with tetrominoes\n{}", USAGE);
+        std::process::exit(2);
+    }
+    let bot = match bot_index.map(|i| &args[i + 1..]) {
+        Some([]) => {
+            eprintln!("--bot needs a command\n{}", USAGE);
+            std::process::exit(2);
+        }
+        Some(command) => match tbp::Bot::spawn(command) {
+            Ok(bot) => Some(bot),
+            Err(e) => {
+                eprintln!("can't launch bot: {}\n{}", e, USAGE);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    // menu is skipped when command line already tells what to play
+    let show_menu = bot.is_none()
+        && ["mode", "edit", "fumen"]
+            .iter()
+            .all(|name| options.get_str(name).is_none());
 
-    loop {
-        let mut frame_count = 0;
-        let current_time = Instant::now();
-        let elasped_time = current_time - previous_frame_time;
-        previous_frame_time = current_time;
-        lag += elasped_time;
+    let mut ui = tui::UI::new().expect("Can't initialize TUI");
+    ui.visual = config.visual.clone();
 
-        // time-wise logic
-        if current_time - previous_arr_time > ARR_TIMEOUT {
-            let das_left = game.das_charge_left.unwrap_or(current_time);
-            let das_right = game.das_charge_right.unwrap_or(current_time);
-            if das_left < das_right {
-                game.player.shift(-1, 0, &game.board);
-            } else if das_right < das_left {
-                game.player.shift(1, 0, &game.board);
+    // control thread
+    let (tx, rx) = mpsc::channel::<Event>();
+    let shared_config = Arc::new(Mutex::new(config.clone()));
+    let control_config = Arc::clone(&shared_config);
+    let key_release = ui.key_release;
+    let signal_tx = tx.clone();
+    thread::spawn(move || handle_controls(tx, control_config, key_release));
+    #[cfg(unix)]
+    thread::spawn(move || control::handle_signals(signal_tx));
+    #[cfg(not(unix))]
+    drop(signal_tx);
+
+    let mut session = Session {
+        options,
+        ui,
+        rx,
+        config,
+        shared_config,
+        pieces,
+        solver_settings,
+        bot,
+        exported: Vec::new(),
+        errors: Vec::new(),
+        lag_frame_count: 0,
+    };
+    let session_start_time = Instant::now();
+
+    if session.options.flag("keys") {
+        let keys = session.config.keys.clone();
+        if let Some(keys) = keymap::run(&mut session.ui, &session.rx, keys) {
+            session.config.keys = keys;
+            if let Err(e) = session.config.save() {
+                session.ui.notice = Some(e);
             }
-            previous_arr_time = current_time;
+            session.apply_config();
+        }
+    }
+
+    if show_menu {
+        session.run_menu();
+    } else {
+        let mode_name = session.options.get_str("mode").unwrap_or("endless");
+        let start = if session.options.flag("edit") {
+            let editor = match pages {
+                Some(pages) => editor::Editor::from_pages(pages),
+                None => editor::Editor::new(),
+            };
+            editor::run(&mut session.ui, &session.rx, editor).map(|editor| Start {
+                board: editor.board,
+                queue: editor.queue,
+                hold: editor.hold,
+            })
+        } else {
+            pages.as_ref().and_then(|pages| pages.first()).map(|page| {
+                let quiz = page.quiz().unwrap_or_default();
+                Start {
+                    board: page.board,
+                    queue: quiz.current.into_iter().chain(quiz.next).collect(),
+                    hold: quiz.hold,
+                }
+            })
+        };
+        if start.is_some() || !session.options.flag("edit") {
+            // mode and rotation were checked above
+            session.play(mode_name, &[], start.as_ref()).unwrap();
         }
+    }
+    session.exit(session_start_time);
+}
 
-        // frame-wise logic
-        while lag >= FRAME_DURATION {
-            frame_count += 1;
-            gravity_frame_count += 1;
+impl Session<'_> {
+    /// game set up with rotation of the mode and rules of config
+    fn new_game(&self, mode: &dyn Mode) -> Result<Game, String> {
+        let rotation = match mode.rotation() {
+            Some(rotation) => rotation,
+            None => rotation::RotationSystem::from_options(&self.options, &self.config.rules)?,
+        };
+        let mut game = Game::new();
+        game.set_rotation(rotation);
+        game.set_pieces(self.piece_set().clone());
+        game.timing.lock_delay = self.config.rules.lock_delay;
+        Ok(game)
+    }
 
-            // TODO: check if softDrop enabled
-            // if enabled change the statement below
-            if gravity_frame_count > GRAVITY_FRAMES {
-                game.shift(0, -1);
-                gravity_frame_count = 0;
-            }
+    /// set given as `--pieces`, the one in config without it
+    fn piece_set(&self) -> &PieceSet {
+        self.pieces.as_ref().unwrap_or(&self.config.rules.piece_set)
+    }
 
-            // lock-delay & infinite placement lock down
-            if game.player.is_bottom() {
-                if let Some(touch_time) = game.last_touch {
-                    if current_time - touch_time > LOCK_DELAY {
-                        game.lock_player();
-                    } else if game.canceled_drop >= 15 {
-                        game.lock_player();
+    /// share changed config with UI and control thread
+    fn apply_config(&mut self) {
+        self.ui.visual = self.config.visual.clone();
+        *self.shared_config.lock().unwrap() = self.config.clone();
+    }
+
+    /// show menus until player quits, games started from them return to menu
+    fn run_menu(&mut self) {
+        let mut menu = Menu::new();
+        loop {
+            let action = menu::run(&mut self.ui, &self.rx, &mut menu, &mut self.config);
+            self.apply_config();
+            match action {
+                MenuAction::Continue => {}
+                MenuAction::Play(name, args) => {
+                    if let Err(e) = self.play(name, &args, None) {
+                        menu.notice = Some(e);
+                    }
+                }
+                MenuAction::Rebind => {
+                    let keys = self.config.keys.clone();
+                    if let Some(keys) = keymap::run(&mut self.ui, &self.rx, keys) {
+                        self.config.keys = keys;
+                        self.apply_config();
+                        menu.notice = Some("keys changed, choose Save to keep them".to_string());
                     }
                 }
+                MenuAction::ViewReplay(index) => {
+                    let pages = match fumen::decode(&menu.replays[index].fumen) {
+                        Ok(pages) => pages,
+                        Err(e) => {
+                            menu.notice = Some(format!("broken replay: {}", e));
+                            continue;
+                        }
+                    };
+                    // starting from the viewer plays endless from shown position
+                    let editor = editor::Editor::from_pages(pages);
+                    if let Some(editor) = editor::run(&mut self.ui, &self.rx, editor) {
+                        let start = Start {
+                            board: editor.board,
+                            queue: editor.queue,
+                            hold: editor.hold,
+                        };
+                        if let Err(e) = self.play("endless", &[], Some(&start)) {
+                            menu.notice = Some(e);
+                        }
+                    }
+                }
+                MenuAction::Quit => break,
             }
+        }
+    }
 
-            ui.render(&game).unwrap();
-            lag -= FRAME_DURATION;
+    /// play mode of the name until game ends or player quits, starting over on restart
+    /// `mode_args` are `--name value` options of the mode, over those from command line
+    fn play(
+        &mut self,
+        mode_name: &str,
+        mode_args: &[String],
+        start: Option<&Start>,
+    ) -> Result<(), String> {
+        let options = self.options.clone().merge(Options::parse(mode_args)?);
+        loop {
+            let mode = mode::from_name(mode_name, &options)?;
+            let mut game = self.new_game(mode.as_ref())?;
+            if let Some(start) = start {
+                game.load(start.board, &start.queue, start.hold);
+            }
+            match self.run_game(game, mode) {
+                Ending::Restart => {}
+                Ending::Finished | Ending::Quit => return Ok(()),
+            }
         }
+    }
 
-        // event-wise logic
-        if let Ok(control) = rx.try_recv() {
-            use ControlKind::*;
-            match control {
-                Quit => {
-                    break;
-                }
-                Left => {
-                    game.shift(-1, 0);
+    /// run one game until it ends, player quits or restarts
+    fn run_game(&mut self, mut game: Game, mut mode: Box<dyn Mode>) -> Ending {
+        // controls sent before game started
+        while self.rx.try_recv().is_ok() {}
+        mode.init(&mut game);
+
+        let game_start_time = Instant::now();
+        let mut previous_frame_time = game_start_time;
+        let mut lag = Duration::from_secs(0);
+
+        let mut previous_arr_time = game_start_time;
+        let mut gravity_time = Duration::ZERO;
+        let mut soft_dropping = false;
+        // auto shift waits until then after lock, for DAS cut delay
+        let mut das_cut_until = game_start_time;
+
+        let mut solutions: Option<solver::Browser> = None;
+        let mut finesse = finesse::Finesse::default();
+        // every placed piece, saved as replay
+        let mut placements = Vec::new();
+
+        let ending = loop {
+            let mut frame_count = 0;
+            let current_time = Instant::now();
+            let elasped_time = current_time - previous_frame_time;
+            previous_frame_time = current_time;
+            lag += elasped_time;
+            game.stats.elapsed += elasped_time;
+
+            // time-wise logic
+            if current_time - previous_arr_time > self.config.handling.arr
+                && current_time >= das_cut_until
+                && game.is_active()
+            {
+                let das_left = game.das_charge_left.unwrap_or(current_time);
+                let das_right = game.das_charge_right.unwrap_or(current_time);
+                let shift = if das_left < das_right {
+                    -1
+                } else if das_right < das_left {
+                    1
+                } else {
+                    0
+                };
+                if shift != 0 {
+                    // ARR of zero moves to the wall at once
+                    while game.player.shift(shift, 0, &game.board)
+                        && self.config.handling.arr.is_zero()
+                    {}
                 }
-                LeftDasStart => {
-                    if game.das_charge_left.is_none() {
-                        game.das_charge_left = Some(current_time)
+                previous_arr_time = current_time;
+            }
+
+            // frame-wise logic
+            while lag >= FRAME_DURATION {
+                frame_count += 1;
+                mode.update(&mut game);
+                game.update_spawn_delay(FRAME_DURATION);
+                gravity_time += FRAME_DURATION;
+
+                // soft drop is gravity sped up by SDF, at least 1 row a second
+                let drop_interval = if soft_dropping {
+                    game.drop_interval().min(Duration::from_secs(1)) / self.config.handling.sdf
+                } else {
+                    game.drop_interval()
+                };
+                while gravity_time >= drop_interval {
+                    if soft_dropping {
+                        game.soft_drop();
+                    } else {
+                        game.shift(0, -1);
                     }
+                    gravity_time -= drop_interval;
                 }
-                LeftDasEnd => {
-                    game.das_charge_left = None;
-                }
-                Right => {
-                    game.shift(1, 0);
-                }
-                RightDasStart => {
-                    if game.das_charge_right.is_none() {
-                        game.das_charge_right = Some(current_time);
+
+                // lock-delay & infinite placement lock down
+                if game.is_active() && game.player.is_bottom() {
+                    if let Some(touch_time) = game.last_touch {
+                        if current_time - touch_time > game.timing.lock_delay
+                            || game.canceled_drop >= 15
+                        {
+                            game.lock_player();
+                        }
                     }
                 }
-                RightDasEnd => {
-                    game.das_charge_right = None;
+
+                // dropping browser cancels solver
+                if solutions.as_ref().is_some_and(|b| !b.is_for(&game.board)) {
+                    solutions = None;
                 }
-                SoftDrop => {
-                    game.shift(0, -1);
+                if let Some(browser) = solutions.as_mut() {
+                    browser.poll();
                 }
-                Rotate => {
-                    game.rotate(Direction::R);
+                self.ui
+                    .render(
+                        &game,
+                        mode.as_ref(),
+                        solutions.as_ref(),
+                        &finesse,
+                        &self.config.keys,
+                    )
+                    .unwrap();
+                lag -= FRAME_DURATION;
+            }
+
+            // event-wise logic
+            // Some when game pauses, with channel to answer suspend when paused
+            let mut pause: Option<Option<Sender<()>>> = None;
+            match self.rx.try_recv() {
+                Ok(Event::Key(key)) => {
+                    if let Some(browser) = solutions.as_mut() {
+                        browser.handle_key(key);
+                    }
                 }
-                RotateCC => {
-                    game.rotate(Direction::L);
+                Ok(Event::Mouse(mouse)) => {
+                    let cell = self.ui.board_cell(mouse.column, mouse.row);
+                    if let (Some(erase), Some((x, y))) = (paint_button(&mouse), cell) {
+                        mode.on_paint(&mut game, x, y, erase);
+                    }
                 }
-                Rotate180 => {
-                    game.rotate(Direction::D);
+                Ok(Event::Suspend(ack)) => pause = Some(Some(ack)),
+                Ok(Event::Resumed) | Ok(Event::FocusLost) => pause = Some(None),
+                Ok(Event::Control(control)) => match control {
+                    ControlKind::Quit => break Ending::Quit,
+                    ControlKind::Restart => break Ending::Restart,
+                    ControlKind::Pause => pause = Some(None),
+                    ControlKind::Export => match fumen::from_game(&game) {
+                        Ok(fumen) => self.exported.push(fumen),
+                        Err(e) => self.ui.notice = Some(format!("can't export: {}", e)),
+                    },
+                    ControlKind::Solve => {
+                        solutions = match solutions {
+                            Some(_) => None,
+                            None => Some(solver::Browser::spawn(
+                                game.board,
+                                game.player.mino_type,
+                                game.hold,
+                                game.bags
+                                    .list
+                                    .iter()
+                                    .rev()
+                                    .take(self.config.visual.preview)
+                                    .copied()
+                                    .collect(),
+                                game.rotation,
+                                self.solver_settings,
+                            )),
+                        }
+                    }
+                    ControlKind::Undo if mode.allows_undo() => {
+                        if game.undo() {
+                            mode.on_control(&mut game, control);
+                        }
+                        finesse.reset();
+                    }
+                    ControlKind::Redo if mode.allows_undo() => {
+                        if game.redo() {
+                            mode.on_control(&mut game, control);
+                        }
+                        finesse.reset();
+                    }
+                    ControlKind::ReloadConfig => match Config::load() {
+                        // rules take effect from next game
+                        Ok(loaded)
+                            if self.bot.is_some()
+                                && self.pieces.is_none()
+                                && !loaded.rules.piece_set.is_empty() =>
+                        {
+                            self.ui.notice =
+                                Some("--bot can only play with tetrominoes".to_string())
+                        }
+                        Ok(loaded) => {
+                            self.config.handling = loaded.handling;
+                            self.config.rules = loaded.rules;
+                            self.config.visual = loaded.visual;
+                            self.config.keys = loaded.keys;
+                            self.ui.notice = None;
+                            self.apply_config();
+                        }
+                        Err(e) => self.ui.notice = Some(e),
+                    },
+                    _ => {
+                        match control {
+                            ControlKind::SoftDrop => soft_dropping = true,
+                            ControlKind::SoftDropEnd => soft_dropping = false,
+                            _ => {}
+                        }
+                        if game.is_active() {
+                            finesse.record(control);
+                            if control.is_piece_input() {
+                                game.stats.keys += 1;
+                            }
+                        }
+                        mode.on_control(&mut game, control);
+                        handle_control(&mut game, control, current_time);
+                    }
+                },
+                _ => {}
+            }
+            if let Some(ack) = pause {
+                let pause_start = Instant::now();
+                if let Some(ending) = self.pause(&mut game, &mut soft_dropping, ack) {
+                    break ending;
                 }
-                HardDrop => {
-                    game.lock_player();
+                // timers stand still while paused
+                let paused = pause_start.elapsed();
+                game.delay_timers(paused);
+                previous_frame_time += paused;
+                previous_arr_time += paused;
+                das_cut_until += paused;
+            }
+
+            // bot-wise logic
+            if let Some(bot) = self.bot.as_mut() {
+                if let Some(controls) = bot.poll(&game) {
+                    for control in controls {
+                        finesse.record(control);
+                        if control.is_piece_input() {
+                            game.stats.keys += 1;
+                        }
+                        handle_control(&mut game, control, current_time);
+                    }
                 }
-                Hold => {
-                    game.swap_hold();
+            }
+
+            for event in game.take_events() {
+                // every event is a lock, next piece waits for DAS cut delay
+                let GameEvent::Lock { piece, board, .. } = &event;
+                placements.push(fumen::placement_page(piece, board));
+                das_cut_until = Instant::now() + self.config.handling.dcd;
+                finesse.on_event(&event);
+                mode.on_event(&mut game, &event);
+            }
+            if game.over || mode.is_finished(&game) {
+                break Ending::Finished;
+            }
+
+            if frame_count > 1 {
+                self.lag_frame_count += frame_count - 1;
+            }
+        };
+
+        // runs thrown away by restart aren't kept
+        if !placements.is_empty() && !matches!(ending, Ending::Restart) {
+            let saved = fumen::encode(&placements).and_then(|fumen| {
+                Replays::add(Replay::new(
+                    mode.name(),
+                    placements.len() as u32,
+                    game.stats.elapsed.as_millis() as u64,
+                    fumen,
+                ))
+                .map_err(|e| e.to_string())
+            });
+            if let Err(e) = saved {
+                self.errors.push(format!("can't save replay: {}", e));
+            }
+        }
+        if !matches!(ending, Ending::Finished) {
+            return ending;
+        }
+        if let Some(record) = mode.record(&game) {
+            if let Err(e) = Records::add(record) {
+                self.errors.push(format!("can't save record: {}", e));
+            }
+        }
+        loop {
+            self.ui
+                .render_results(&game, mode.as_ref(), &finesse, &self.config.keys)
+                .unwrap();
+            match self.rx.recv() {
+                Ok(Event::Control(ControlKind::Quit)) | Err(_) => return Ending::Finished,
+                Ok(Event::Control(ControlKind::Restart)) => return Ending::Restart,
+                Ok(Event::Suspend(ack)) => {
+                    let _ = ack.send(());
                 }
+                _ => {}
             }
         }
+    }
 
-        if frame_count > 1 {
-            lag_frame_count += frame_count - 1;
+    /// show pause menu until player resumes, None when game goes on
+    /// key releases are still applied so no key is left held down
+    fn pause(
+        &mut self,
+        game: &mut Game,
+        soft_dropping: &mut bool,
+        mut ack: Option<Sender<()>>,
+    ) -> Option<Ending> {
+        let mut cursor = 0;
+        loop {
+            self.ui.render_pause(cursor).unwrap();
+            if let Some(ack) = ack.take() {
+                let _ = ack.send(());
+            }
+            let mut choose = false;
+            match self.rx.recv() {
+                Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => cursor = cursor.saturating_sub(1),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        cursor = (cursor + 1).min(tui::PAUSE_ITEMS.len() - 1)
+                    }
+                    KeyCode::Enter | KeyCode::Char(' ') => choose = true,
+                    _ => {}
+                },
+                Ok(Event::Mouse(mouse)) => match mouse.kind {
+                    MouseEventKind::ScrollUp => cursor = cursor.saturating_sub(1),
+                    MouseEventKind::ScrollDown => {
+                        cursor = (cursor + 1).min(tui::PAUSE_ITEMS.len() - 1)
+                    }
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        if let Some(row) = self.ui.pause_row(mouse.column, mouse.row) {
+                            cursor = row;
+                            choose = true;
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(Event::Control(control)) => match control {
+                    ControlKind::Pause => break None,
+                    ControlKind::Restart => break Some(Ending::Restart),
+                    ControlKind::Quit => break Some(Ending::Quit),
+                    ControlKind::LeftDasEnd | ControlKind::RightDasEnd => {
+                        handle_control(game, control, Instant::now())
+                    }
+                    ControlKind::SoftDropEnd => *soft_dropping = false,
+                    _ => {}
+                },
+                Ok(Event::Suspend(next)) => ack = Some(next),
+                Err(_) => break Some(Ending::Quit),
+                _ => {}
+            }
+            if choose {
+                break match cursor {
+                    0 => None,
+                    1 => Some(Ending::Restart),
+                    _ => Some(Ending::Quit),
+                };
+            }
         }
     }
 
-    ui.exit().expect("Error while exiting program");
+    fn exit(mut self, start_time: Instant) {
+        self.ui.exit().expect("Error while exiting program");
 
-    println!(
-        "{} frames lagged for {:?}",
-        lag_frame_count,
-        game_start_time.elapsed()
-    );
+        println!(
+            "{} frames lagged for {:?}",
+            self.lag_frame_count,
+            start_time.elapsed()
+        );
+        for fumen in &self.exported {
+            println!("{}", fumen);
+        }
+        for e in &self.errors {
+            println!("{}", e);
+        }
+        if let Some(bot) = self.bot {
+            if let Some(name) = &bot.name {
+                println!("played by {}", name);
+            }
+            if let Some(error) = bot.error() {
+                println!("bot stopped: {}", error);
+            }
+        }
+    }
+}
+
+fn handle_control(game: &mut Game, control: ControlKind, current_time: Instant) {
+    use ControlKind::*;
+    match control {
+        Quit => {}
+        Left => {
+            game.shift(-1, 0);
+        }
+        LeftDasStart => {
+            if game.das_charge_left.is_none() {
+                game.das_charge_left = Some(current_time)
+            }
+        }
+        LeftDasEnd => {
+            game.das_charge_left = None;
+        }
+        Right => {
+            game.shift(1, 0);
+        }
+        RightDasStart => {
+            if game.das_charge_right.is_none() {
+                game.das_charge_right = Some(current_time);
+            }
+        }
+        RightDasEnd => {
+            game.das_charge_right = None;
+        }
+        SoftDrop => {
+            game.soft_drop();
+        }
+        // gravity stops being sped up in main loop
+        SoftDropEnd => {}
+        Rotate => {
+            game.rotate(Direction::R);
+        }
+        RotateCC => {
+            game.rotate(Direction::L);
+        }
+        Rotate180 => {
+            game.rotate(Direction::D);
+        }
+        HardDrop => {
+            game.hard_drop();
+        }
+        Hold => {
+            game.swap_hold();
+        }
+        // handled by modes and main loop
+        Undo | Redo | CyclePiece | CycleHold | Export | Solve | ReloadConfig | Pause | Restart => {}
+    }
 }
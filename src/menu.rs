@@ -0,0 +1,455 @@
+//! Menus around games: title, mode select, settings, records and replays
+
+use std::{sync::mpsc::Receiver, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::{
+    config::{Config, MAX_MILLIS},
+    core::MAX_PREVIEW,
+    mode::{
+        format_time, load_setups, CHEESE_TARGETS, DEFAULT_CHEESE_TARGET, DEFAULT_MARATHON_GOAL,
+        DEFAULT_SPRINT_GOAL, DEFAULT_TIMED_MINUTES, MARATHON_GOALS, MODE_NAMES, SPRINT_GOALS,
+        TIMED_MINUTES,
+    },
+    records::{format_date, Record, Records, Replay, Replays},
+    tui::{Theme, THEME_NAMES, UI},
+    Event,
+};
+
+const TITLE_ITEMS: [&str; 5] = ["Play", "Settings", "Records", "Replays", "Quit"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Title,
+    Modes,
+    Settings,
+    Records,
+    Replays,
+}
+
+/// rows of settings screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    Das,
+    Arr,
+    Sdf,
+    Dcd,
+    Preview,
+    Ghost,
+    Theme,
+    Keys,
+    Reload,
+    Save,
+    Back,
+}
+
+const SETTINGS: [Setting; 11] = [
+    Setting::Das,
+    Setting::Arr,
+    Setting::Sdf,
+    Setting::Dcd,
+    Setting::Preview,
+    Setting::Ghost,
+    Setting::Theme,
+    Setting::Keys,
+    Setting::Reload,
+    Setting::Save,
+    Setting::Back,
+];
+
+/// rows of mode screen after mode names, passed to modes they belong to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModeOption {
+    SprintGoal,
+    TimedMinutes,
+    MarathonGoal,
+    CheeseTarget,
+    Opener,
+}
+
+const MODE_OPTIONS: [ModeOption; 5] = [
+    ModeOption::SprintGoal,
+    ModeOption::TimedMinutes,
+    ModeOption::MarathonGoal,
+    ModeOption::CheeseTarget,
+    ModeOption::Opener,
+];
+
+impl ModeOption {
+    /// (row label, option name, modes using it)
+    fn info(self) -> (&'static str, &'static str, &'static [&'static str]) {
+        match self {
+            ModeOption::SprintGoal => ("Sprint goal", "goal", &["sprint"]),
+            ModeOption::TimedMinutes => ("Minutes", "minutes", &["blitz", "ultra"]),
+            ModeOption::MarathonGoal => ("Marathon goal", "goal", &["marathon"]),
+            ModeOption::CheeseTarget => ("Cheese lines", "target", &["cheese"]),
+            ModeOption::Opener => ("Opener", "opener", &["opener"]),
+        }
+    }
+
+    /// values to pick from, `openers` are names of loaded setups
+    fn choices(self, openers: &[String]) -> Vec<String> {
+        match self {
+            ModeOption::SprintGoal => SPRINT_GOALS.map(|goal| goal.to_string()).to_vec(),
+            ModeOption::TimedMinutes => TIMED_MINUTES.map(|minutes| minutes.to_string()).to_vec(),
+            ModeOption::MarathonGoal => MARATHON_GOALS.map(String::from).to_vec(),
+            ModeOption::CheeseTarget => CHEESE_TARGETS.map(|target| target.to_string()).to_vec(),
+            ModeOption::Opener => openers.to_vec(),
+        }
+    }
+
+    fn default_choice(self) -> usize {
+        let default = match self {
+            ModeOption::SprintGoal => DEFAULT_SPRINT_GOAL.to_string(),
+            ModeOption::TimedMinutes => DEFAULT_TIMED_MINUTES.to_string(),
+            ModeOption::MarathonGoal => DEFAULT_MARATHON_GOAL.to_string(),
+            ModeOption::CheeseTarget => DEFAULT_CHEESE_TARGET.to_string(),
+            ModeOption::Opener => return 0,
+        };
+        self.choices(&[])
+            .iter()
+            .position(|choice| *choice == default)
+            .unwrap_or(0)
+    }
+}
+
+pub enum MenuAction {
+    Continue,
+    /// play mode of the name with `--name value` options picked on mode screen
+    Play(&'static str, Vec<String>),
+    /// open key rebind screen
+    Rebind,
+    /// show replay at index of `Menu::replays`
+    ViewReplay(usize),
+    Quit,
+}
+
+pub struct Menu {
+    pub screen: Screen,
+    /// selected row of current screen
+    pub cursor: usize,
+    /// newest first
+    records: Vec<Record>,
+    /// newest first
+    pub replays: Vec<Replay>,
+    /// result of last action, like config errors
+    pub notice: Option<String>,
+    /// index into choices of each of `MODE_OPTIONS`
+    mode_choices: [usize; MODE_OPTIONS.len()],
+    /// names of opener setups, loaded when mode screen opens
+    openers: Vec<String>,
+}
+
+/// step milliseconds of `value`, kept within accepted range
+fn step_millis(value: Duration, step: i64) -> Duration {
+    let millis = (value.as_millis() as i64 + step).clamp(0, MAX_MILLIS);
+    Duration::from_millis(millis as u64)
+}
+
+impl Menu {
+    pub fn new() -> Menu {
+        Menu {
+            screen: Screen::Title,
+            cursor: 0,
+            records: Vec::new(),
+            replays: Vec::new(),
+            notice: None,
+            mode_choices: MODE_OPTIONS.map(ModeOption::default_choice),
+            openers: Vec::new(),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.screen {
+            Screen::Title => "TET-RS",
+            Screen::Modes => "MODE",
+            Screen::Settings => "SETTINGS",
+            Screen::Records => "RECORDS",
+            Screen::Replays => "REPLAYS",
+        }
+    }
+
+    /// key help shown next to menu
+    pub fn help(&self) -> Vec<&'static str> {
+        let mut help = vec!["jk/arrows   select"];
+        if matches!(self.screen, Screen::Modes | Screen::Settings) {
+            help.push("hl/arrows   change");
+        }
+        if self.screen != Screen::Records {
+            help.push("enter       choose");
+        }
+        help.push("mouse       click, scroll");
+        help.push(if self.screen == Screen::Title {
+            "q/esc       quit"
+        } else {
+            "q/esc       back"
+        });
+        help
+    }
+
+    /// rows of current screen
+    pub fn items(&self, config: &Config) -> Vec<String> {
+        match self.screen {
+            Screen::Title => TITLE_ITEMS.map(String::from).to_vec(),
+            Screen::Modes => {
+                let mut items = MODE_NAMES.map(String::from).to_vec();
+                items.extend(MODE_OPTIONS.iter().enumerate().map(|(i, option)| {
+                    let (label, _, _) = option.info();
+                    let choices = option.choices(&self.openers);
+                    let value = choices.get(self.mode_choices[i]).map_or("", String::as_str);
+                    format!("{:<14}{}", label, value)
+                }));
+                items
+            }
+            Screen::Settings => SETTINGS
+                .iter()
+                .map(|setting| setting_line(*setting, config))
+                .collect(),
+            Screen::Records if self.records.is_empty() => vec!["no records yet".to_string()],
+            Screen::Records => self
+                .records
+                .iter()
+                .map(|record| {
+                    format!(
+                        "{} {:<12.12} {:>8} {:>4}L {}",
+                        format_date(record.date),
+                        record.mode,
+                        record.score,
+                        record.lines,
+                        format_time(Duration::from_millis(record.time_ms))
+                    )
+                })
+                .collect(),
+            Screen::Replays if self.replays.is_empty() => vec!["no replays yet".to_string()],
+            Screen::Replays => self
+                .replays
+                .iter()
+                .map(|replay| {
+                    format!(
+                        "{} {:<12.12} {:>4}P {}",
+                        format_date(replay.date),
+                        replay.mode,
+                        replay.pieces,
+                        format_time(Duration::from_millis(replay.time_ms))
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn open(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.cursor = 0;
+        self.notice = None;
+        match screen {
+            Screen::Modes => match load_setups() {
+                Ok(setups) => self.openers = setups.into_iter().map(|setup| setup.name).collect(),
+                Err(e) => self.notice = Some(format!("can't load openers: {}", e)),
+            },
+            Screen::Records => match Records::load() {
+                Ok(records) => {
+                    self.records = records.records;
+                    self.records.reverse();
+                }
+                Err(e) => self.notice = Some(format!("can't load records: {}", e)),
+            },
+            Screen::Replays => match Replays::load() {
+                Ok(replays) => {
+                    self.replays = replays.replays;
+                    self.replays.reverse();
+                }
+                Err(e) => self.notice = Some(format!("can't load replays: {}", e)),
+            },
+            _ => {}
+        }
+    }
+
+    fn back(&mut self) -> MenuAction {
+        let index = match self.screen {
+            Screen::Title => return MenuAction::Quit,
+            Screen::Modes => 0,
+            Screen::Settings => 1,
+            Screen::Records => 2,
+            Screen::Replays => 3,
+        };
+        self.open(Screen::Title);
+        self.cursor = index;
+        MenuAction::Continue
+    }
+
+    fn move_cursor(&mut self, step: isize, config: &Config) {
+        let len = self.items(config).len();
+        self.cursor = self.cursor.saturating_add_signed(step).min(len - 1);
+    }
+
+    /// enter key or click on selected row
+    fn choose(&mut self, config: &mut Config) -> MenuAction {
+        match self.screen {
+            Screen::Title => match self.cursor {
+                0 => self.open(Screen::Modes),
+                1 => self.open(Screen::Settings),
+                2 => self.open(Screen::Records),
+                3 => self.open(Screen::Replays),
+                _ => return MenuAction::Quit,
+            },
+            Screen::Modes if self.cursor < MODE_NAMES.len() => {
+                return MenuAction::Play(MODE_NAMES[self.cursor], self.mode_args(self.cursor))
+            }
+            Screen::Modes => self.change(1, config),
+            Screen::Settings => match SETTINGS[self.cursor] {
+                Setting::Keys => return MenuAction::Rebind,
+                Setting::Reload => match Config::load() {
+                    Ok(loaded) => {
+                        *config = loaded;
+                        self.notice = Some("config reloaded".to_string());
+                    }
+                    Err(e) => self.notice = Some(e),
+                },
+                Setting::Save => {
+                    self.notice = Some(match config.save() {
+                        Ok(()) => "settings saved".to_string(),
+                        Err(e) => e,
+                    })
+                }
+                Setting::Back => return self.back(),
+                _ => self.change(1, config),
+            },
+            Screen::Records => {}
+            Screen::Replays if self.replays.is_empty() => {}
+            Screen::Replays => return MenuAction::ViewReplay(self.cursor),
+        }
+        MenuAction::Continue
+    }
+
+    /// options picked for mode at `index` of `MODE_NAMES`
+    fn mode_args(&self, index: usize) -> Vec<String> {
+        let mut args = Vec::new();
+        for (i, option) in MODE_OPTIONS.iter().enumerate() {
+            let (_, name, modes) = option.info();
+            if !modes.contains(&MODE_NAMES[index]) {
+                continue;
+            }
+            if let Some(value) = option.choices(&self.openers).get(self.mode_choices[i]) {
+                args.extend([format!("--{}", name), value.clone()]);
+            }
+        }
+        args
+    }
+
+    /// change selected setting or mode option up or down
+    fn change(&mut self, step: i64, config: &mut Config) {
+        match self.screen {
+            Screen::Modes if self.cursor >= MODE_NAMES.len() => {
+                let i = self.cursor - MODE_NAMES.len();
+                let count = MODE_OPTIONS[i].choices(&self.openers).len() as i64;
+                if count > 0 {
+                    let choice = (self.mode_choices[i] as i64 + step).rem_euclid(count);
+                    self.mode_choices[i] = choice as usize;
+                }
+                return;
+            }
+            Screen::Settings => {}
+            _ => return,
+        }
+        let handling = &mut config.handling;
+        let visual = &mut config.visual;
+        match SETTINGS[self.cursor] {
+            Setting::Das => handling.das = step_millis(handling.das, step * 10),
+            Setting::Arr => handling.arr = step_millis(handling.arr, step),
+            Setting::Sdf => handling.sdf = (handling.sdf as i64 + step).clamp(1, 1000) as u32,
+            Setting::Dcd => handling.dcd = step_millis(handling.dcd, step * 10),
+            Setting::Preview => {
+                visual.preview =
+                    (visual.preview as i64 + step).clamp(0, MAX_PREVIEW as i64) as usize
+            }
+            Setting::Ghost => visual.ghost = !visual.ghost,
+            Setting::Theme => {
+                let index = (visual.theme as i64 + step).rem_euclid(THEME_NAMES.len() as i64);
+                visual.theme = Theme::from_name(THEME_NAMES[index as usize]).unwrap_or_default();
+            }
+            Setting::Keys | Setting::Reload | Setting::Save | Setting::Back => {}
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, config: &mut Config) -> MenuAction {
+        if key.kind == KeyEventKind::Release {
+            return MenuAction::Continue;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1, config),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1, config),
+            KeyCode::Left | KeyCode::Char('h') => self.change(-1, config),
+            KeyCode::Right | KeyCode::Char('l') => self.change(1, config),
+            KeyCode::Enter | KeyCode::Char(' ') => return self.choose(config),
+            KeyCode::Esc | KeyCode::Char('q') => return self.back(),
+            _ => {}
+        }
+        MenuAction::Continue
+    }
+
+    /// `row` is the menu row under mouse
+    pub fn handle_mouse(
+        &mut self,
+        mouse: MouseEvent,
+        row: Option<usize>,
+        config: &mut Config,
+    ) -> MenuAction {
+        let len = self.items(config).len();
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.move_cursor(-1, config),
+            MouseEventKind::ScrollDown => self.move_cursor(1, config),
+            MouseEventKind::Down(button) => {
+                let Some(row) = row.filter(|row| *row < len) else {
+                    return MenuAction::Continue;
+                };
+                self.cursor = row;
+                match button {
+                    MouseButton::Left => return self.choose(config),
+                    MouseButton::Right => self.change(-1, config),
+                    MouseButton::Middle => {}
+                }
+            }
+            _ => {}
+        }
+        MenuAction::Continue
+    }
+}
+
+fn setting_line(setting: Setting, config: &Config) -> String {
+    let handling = &config.handling;
+    let visual = &config.visual;
+    let (name, value) = match setting {
+        Setting::Das => ("DAS", format!("{}ms", handling.das.as_millis())),
+        Setting::Arr => ("ARR", format!("{}ms", handling.arr.as_millis())),
+        Setting::Sdf => ("SDF", format!("{}x", handling.sdf)),
+        Setting::Dcd => ("DCD", format!("{}ms", handling.dcd.as_millis())),
+        Setting::Preview => ("Preview", visual.preview.to_string()),
+        Setting::Ghost => ("Ghost", if visual.ghost { "on" } else { "off" }.to_string()),
+        Setting::Theme => ("Theme", visual.theme.name().to_string()),
+        Setting::Keys => ("Keys...", String::new()),
+        Setting::Reload => ("Reload config", String::new()),
+        Setting::Save => ("Save", String::new()),
+        Setting::Back => ("Back", String::new()),
+    };
+    format!("{:<14}{}", name, value)
+}
+
+/// run menu until player chooses something other than moving around it
+pub fn run(ui: &mut UI, rx: &Receiver<Event>, menu: &mut Menu, config: &mut Config) -> MenuAction {
+    loop {
+        ui.render_menu(menu, config).unwrap();
+        let action = match rx.recv() {
+            Ok(Event::Key(key)) => menu.handle_key(key, config),
+            Ok(Event::Mouse(mouse)) => {
+                let row = ui.menu_row(mouse.column, mouse.row, menu.cursor);
+                menu.handle_mouse(mouse, row, config)
+            }
+            Ok(_) => MenuAction::Continue,
+            Err(_) => MenuAction::Quit,
+        };
+        if !matches!(action, MenuAction::Continue) {
+            return action;
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Game modes decide when the game ends and what to show besides the board
+
+mod cheese;
+mod endless;
+mod finesse_trainer;
+mod marathon;
+mod master;
+mod opener;
+mod sprint;
+mod survival;
+mod timed;
+mod zen;
+
+use std::{collections::HashMap, fmt::Display, str::FromStr, time::Duration};
+
+use crate::{
+    core::{Game, GameEvent, MinoType},
+    records::Record,
+    rotation::RotationSystem,
+    ControlKind,
+};
+
+pub use cheese::{Cheese, CHEESE_TARGETS, DEFAULT_CHEESE_TARGET};
+pub use endless::Endless;
+pub use finesse_trainer::FinesseTrainer;
+pub use marathon::{Marathon, DEFAULT_MARATHON_GOAL, MARATHON_GOALS};
+pub use master::Master;
+pub use opener::{load_setups, Opener};
+pub use sprint::{Sprint, DEFAULT_SPRINT_GOAL, SPRINT_GOALS};
+pub use survival::Survival;
+pub use timed::{Timed, TimedKind, DEFAULT_TIMED_MINUTES, TIMED_MINUTES};
+pub use zen::Zen;
+
+pub const MODE_NAMES: [&str; 11] = [
+    "endless", "sprint", "blitz", "ultra", "marathon", "cheese", "survival", "master", "zen",
+    "finesse", "opener",
+];
+
+pub trait Mode {
+    fn name(&self) -> String;
+
+    /// rotation system the mode is played with, `None` uses `--rotation`
+    fn rotation(&self) -> Option<RotationSystem> {
+        None
+    }
+
+    /// called once before game starts
+    fn init(&mut self, _game: &mut Game) {}
+
+    /// called for every event `Game` emitted
+    fn on_event(&mut self, _game: &mut Game, _event: &GameEvent) {}
+
+    /// called for every control before it's applied to `Game`
+    /// undo and redo come after they were done, only when they changed `Game`
+    fn on_control(&mut self, _game: &mut Game, _control: ControlKind) {}
+
+    /// called when board cell at (x, y) is clicked
+    fn on_paint(&mut self, _game: &mut Game, _x: i8, _y: i8, _erase: bool) {}
+
+    /// called every frame while game is running
+    fn update(&mut self, _game: &mut Game) {}
+
+    /// true when goal of the mode is reached
+    fn is_finished(&self, game: &Game) -> bool;
+
+    /// lines shown next to the board while playing
+    fn hud(&self, game: &Game) -> Vec<String>;
+
+    /// controls explained below HUD, with the keys bound to them
+    fn controls(&self) -> Vec<(ControlKind, &'static str)> {
+        Vec::new()
+    }
+
+    /// lines shown after the game ended
+    fn results(&self, game: &Game) -> Vec<String>;
+
+    /// cells drawn over the board as a guide, like a target placement
+    fn overlay(&self, _game: &Game) -> Vec<(i8, i8, MinoType)> {
+        Vec::new()
+    }
+
+    /// practice modes let player undo and redo placements
+    fn allows_undo(&self) -> bool {
+        false
+    }
+
+    /// record to keep after the game ended
+    fn record(&self, _game: &Game) -> Option<Record> {
+        None
+    }
+}
+
+/// mode options given as `--name value` pairs
+#[derive(Clone)]
+pub struct Options<'a> {
+    values: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Options<'a> {
+    /// option without value, like `--edit`, is a flag set to "true"
+    pub fn parse(args: &'a [String]) -> Result<Options<'a>, String> {
+        let mut values = HashMap::new();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unknown argument: {}", arg))?;
+            let value = match args.peek() {
+                Some(value) if !value.starts_with("--") => args.next().unwrap(),
+                _ => "true",
+            };
+            values.insert(name, value);
+        }
+        Ok(Options { values })
+    }
+
+    /// options of both, values of `other` win
+    pub fn merge(mut self, other: Options<'a>) -> Options<'a> {
+        self.values.extend(other.values);
+        self
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.get_str(name) == Some("true")
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&'a str> {
+        self.values.get(name).copied()
+    }
+
+    /// parse value of option, `None` when option isn't given
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.get_str(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value for --{}: {}", name, value))
+            })
+            .transpose()
+    }
+
+    /// like `get` but value should be one of `choices`
+    pub fn get_choice<T: FromStr + PartialEq + Display>(
+        &self,
+        name: &str,
+        choices: &[T],
+        default: T,
+    ) -> Result<T, String> {
+        let value = self.get(name)?.unwrap_or(default);
+        if choices.contains(&value) {
+            Ok(value)
+        } else {
+            let choices: Vec<String> = choices.iter().map(T::to_string).collect();
+            Err(format!(
+                "--{} should be one of {}",
+                name,
+                choices.join(", ")
+            ))
+        }
+    }
+}
+
+/// create mode from `--mode` and its options
+pub fn from_options(options: &Options) -> Result<Box<dyn Mode>, String> {
+    from_name(options.get_str("mode").unwrap_or("endless"), options)
+}
+
+/// create mode chosen by name, other options of the mode are read from `options`
+pub fn from_name(name: &str, options: &Options) -> Result<Box<dyn Mode>, String> {
+    Ok(match name {
+        "endless" => Box::new(Endless),
+        "sprint" => Box::new(Sprint::from_options(options)?),
+        "blitz" => Box::new(Timed::from_options(TimedKind::Blitz, options)?),
+        "ultra" => Box::new(Timed::from_options(TimedKind::Ultra, options)?),
+        "marathon" => Box::new(Marathon::from_options(options)?),
+        "cheese" => Box::new(Cheese::from_options(options)?),
+        "survival" => Box::new(Survival::new()),
+        "master" => Box::new(Master::new()),
+        "zen" => Box::new(Zen),
+        "finesse" => Box::new(FinesseTrainer::new()),
+        "opener" => Box::new(Opener::from_options(options)?),
+        _ => {
+            return Err(format!(
+                "unknown mode: {}, available modes are {}",
+                name,
+                MODE_NAMES.join(", ")
+            ))
+        }
+    })
+}
+
+/// format duration as `m:ss.mmm`
+pub fn format_time(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
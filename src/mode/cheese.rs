@@ -0,0 +1,110 @@
+use crate::{
+    core::{random_hole, Game, GameEvent, GRID_HEIGHT},
+    records::Record,
+};
+
+use super::{format_time, Mode, Options};
+
+pub const CHEESE_TARGETS: [u32; 3] = [10, 18, 100];
+pub const DEFAULT_CHEESE_TARGET: u32 = 18;
+const DEFAULT_CHEESE_ROWS: u32 = 10;
+/// rows kept free of garbage so new Mino can spawn
+const SPAWN_ROWS: u32 = 6;
+
+/// dig through `target` garbage lines while board is kept topped up to `rows`
+pub struct Cheese {
+    rows: u32,
+    target: u32,
+    /// number of garbage rows pushed so far
+    added: u32,
+    last_hole: Option<usize>,
+}
+
+impl Cheese {
+    pub fn new(rows: u32, target: u32) -> Cheese {
+        Cheese {
+            rows,
+            target,
+            added: 0,
+            last_hole: None,
+        }
+    }
+
+    /// `--target` is one of `CHEESE_TARGETS`, `--rows` is number of garbage rows kept
+    pub fn from_options(options: &Options) -> Result<Cheese, String> {
+        let target = options.get_choice("target", &CHEESE_TARGETS, DEFAULT_CHEESE_TARGET)?;
+        let rows = options.get("rows")?.unwrap_or(DEFAULT_CHEESE_ROWS);
+        let max_rows = GRID_HEIGHT as u32 - SPAWN_ROWS;
+        if rows == 0 || rows > max_rows {
+            return Err(format!("--rows should be between 1 and {}", max_rows));
+        }
+        Ok(Cheese::new(rows, target))
+    }
+
+    /// push garbage until board has `rows` garbage rows or target is reached
+    fn top_up(&mut self, game: &mut Game) {
+        let remaining = game.board.garbage_rows() as u32;
+        let count = self
+            .rows
+            .saturating_sub(remaining)
+            .min(self.target - self.added);
+        let holes: Vec<usize> = (0..count)
+            .map(|_| {
+                let hole = random_hole(self.last_hole);
+                self.last_hole = Some(hole);
+                hole
+            })
+            .collect();
+        game.add_garbage(&holes);
+        self.added += count;
+    }
+}
+
+impl Mode for Cheese {
+    fn name(&self) -> String {
+        format!("Cheese {}L", self.target)
+    }
+
+    fn init(&mut self, game: &mut Game) {
+        self.top_up(game);
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: &GameEvent) {
+        match event {
+            GameEvent::Lock { cleared, .. } if *cleared > 0 => {
+                self.top_up(game);
+            }
+            _ => {}
+        }
+    }
+
+    fn is_finished(&self, game: &Game) -> bool {
+        game.stats.garbage_cleared >= self.target
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Time   {}", format_time(game.stats.elapsed)),
+            format!("Dug    {}/{}", game.stats.garbage_cleared, self.target),
+            format!("Pieces {}", game.stats.pieces),
+            format!("PPS    {:.2}", game.stats.pps()),
+        ]
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        self.hud(game)
+    }
+
+    /// only fully dug games are kept
+    fn record(&self, game: &Game) -> Option<Record> {
+        self.is_finished(game).then(|| {
+            Record::new(
+                self.name(),
+                game.score,
+                game.stats.lines,
+                game.level,
+                game.stats.elapsed.as_millis() as u64,
+            )
+        })
+    }
+}
@@ -0,0 +1,29 @@
+use crate::core::Game;
+
+use super::{format_time, Mode};
+
+/// play until top out
+pub struct Endless;
+
+impl Mode for Endless {
+    fn name(&self) -> String {
+        "Endless".to_string()
+    }
+
+    fn is_finished(&self, _game: &Game) -> bool {
+        false
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Time   {}", format_time(game.stats.elapsed)),
+            format!("Score  {}", game.score),
+            format!("Lines  {}", game.stats.lines),
+            format!("Pieces {}", game.stats.pieces),
+        ]
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        self.hud(game)
+    }
+}
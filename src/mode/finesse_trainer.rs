@@ -0,0 +1,198 @@
+use std::{collections::HashMap, time::Duration};
+
+use rand::seq::SliceRandom;
+
+use crate::{
+    core::{Board, Direction, Game, GameEvent, Mino, MinoType, Timing},
+    finesse::Finesse,
+    movegen::{finesse_path, placements, same_place},
+    pieces, ControlKind,
+};
+
+use super::Mode;
+
+const DIRECTIONS: [Direction; 4] = [Direction::Z, Direction::R, Direction::D, Direction::L];
+
+/// name of input shown when player missed
+fn input_name(input: ControlKind) -> &'static str {
+    match input {
+        ControlKind::Left => "left",
+        ControlKind::Right => "right",
+        ControlKind::LeftDasStart => "DAS left",
+        ControlKind::RightDasStart => "DAS right",
+        ControlKind::Rotate => "cw",
+        ControlKind::RotateCC => "ccw",
+        ControlKind::Rotate180 => "180",
+        _ => "?",
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Score {
+    tries: u32,
+    correct: u32,
+}
+
+impl Score {
+    fn add(&mut self, correct: bool) {
+        self.tries += 1;
+        self.correct += correct as u32;
+    }
+}
+
+/// place pieces on shown targets with minimum inputs
+/// missed piece is retried after showing the correct inputs
+pub struct FinesseTrainer {
+    /// board every piece starts from
+    preset: Board,
+    target: Option<Mino>,
+    /// inputs used for current piece
+    finesse: Finesse,
+    scores: HashMap<(MinoType, Direction), Score>,
+    /// result of last piece
+    message: Vec<String>,
+}
+
+impl FinesseTrainer {
+    pub fn new() -> FinesseTrainer {
+        FinesseTrainer {
+            preset: Board::new(),
+            target: None,
+            finesse: Finesse::default(),
+            scores: HashMap::new(),
+            message: Vec::new(),
+        }
+    }
+
+    /// random placement reachable without soft drop
+    fn pick_target(&self, game: &Game) -> Option<Mino> {
+        let spawned = Mino::new(
+            game.player.mino_type,
+            game.rotation,
+            &game.pieces,
+            &game.board,
+        );
+        let targets: Vec<Mino> = placements(&spawned, &game.board)
+            .into_iter()
+            .filter(|target| finesse_path(&spawned, target, &game.board).is_some())
+            .collect();
+        targets.choose(&mut rand::thread_rng()).cloned()
+    }
+
+    fn score(&self, mino_type: MinoType) -> Score {
+        DIRECTIONS
+            .iter()
+            .filter_map(|&direction| self.scores.get(&(mino_type, direction)))
+            .fold(Score::default(), |total, score| Score {
+                tries: total.tries + score.tries,
+                correct: total.correct + score.correct,
+            })
+    }
+}
+
+impl Mode for FinesseTrainer {
+    fn name(&self) -> String {
+        "Finesse".to_string()
+    }
+
+    fn init(&mut self, game: &mut Game) {
+        game.timing = Timing {
+            gravity: Some(Duration::MAX),
+            ..Timing::default()
+        };
+        self.preset = game.board;
+    }
+
+    fn update(&mut self, game: &mut Game) {
+        let needs_target = match &self.target {
+            Some(target) => target.mino_type != game.player.mino_type,
+            None => true,
+        };
+        if needs_target && game.is_active() {
+            self.target = self.pick_target(game);
+            self.finesse.reset();
+        }
+    }
+
+    fn on_control(&mut self, game: &mut Game, control: ControlKind) {
+        match control {
+            ControlKind::Undo | ControlKind::Redo => self.finesse.reset(),
+            _ if game.is_active() => self.finesse.record(control),
+            _ => {}
+        }
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: &GameEvent) {
+        let GameEvent::Lock { piece, board, .. } = event;
+        let Some(target) = self.target.clone() else {
+            return;
+        };
+        let path = Finesse::optimal_inputs(piece, &target, board).unwrap_or_default();
+        let correct = same_place(piece, &target) && !self.finesse.is_fault(&path);
+        self.scores
+            .entry((target.mino_type, target.direction))
+            .or_default()
+            .add(correct);
+        self.finesse.reset();
+        if correct {
+            self.message = vec!["Correct!".to_string()];
+            self.target = None;
+            game.load(self.preset, &[], game.hold);
+        } else {
+            let mut names: Vec<&str> = path.into_iter().map(input_name).collect();
+            names.push("hard drop");
+            self.message = vec!["Missed, correct inputs are".to_string(), names.join(" ")];
+            // retry same piece
+            game.take_back();
+        }
+    }
+
+    fn overlay(&self, _game: &Game) -> Vec<(i8, i8, MinoType)> {
+        match &self.target {
+            Some(target) => target
+                .get_ghost()
+                .get_cells()
+                .into_iter()
+                .map(|(x, y)| (x, y, target.mino_type))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn is_finished(&self, _game: &Game) -> bool {
+        false
+    }
+
+    fn allows_undo(&self) -> bool {
+        true
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        let mut lines = self.message.clone();
+        lines.push(String::new());
+        lines.extend(self.results(game));
+        lines
+    }
+
+    /// per piece type, then per orientation
+    fn results(&self, game: &Game) -> Vec<String> {
+        let mut lines = Vec::new();
+        for mino_type in pieces::mino_types(&game.pieces) {
+            let total = self.score(mino_type);
+            lines.push(format!("{:?} {}/{}", mino_type, total.correct, total.tries));
+            let scores: Vec<String> = DIRECTIONS
+                .iter()
+                .map(|&direction| {
+                    let score = self
+                        .scores
+                        .get(&(mino_type, direction))
+                        .copied()
+                        .unwrap_or_default();
+                    format!("{:?} {}/{}", direction, score.correct, score.tries)
+                })
+                .collect();
+            lines.push(format!("  {}", scores.join(" ")));
+        }
+        lines
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    core::{Game, GameEvent},
+    records::Record,
+};
+
+use super::{format_time, Mode, Options};
+
+pub const MARATHON_GOALS: [&str; 3] = ["150", "200", "endless"];
+pub const DEFAULT_MARATHON_GOAL: &str = "150";
+/// lines needed to go to next level
+const LEVEL_LINES: u32 = 10;
+/// last level of gravity table
+const MAX_LEVEL: u32 = 20;
+
+/// level up every `LEVEL_LINES` lines
+pub struct Marathon {
+    /// `None` for endless marathon
+    goal: Option<u32>,
+}
+
+impl Marathon {
+    pub fn new(goal: Option<u32>) -> Marathon {
+        Marathon { goal }
+    }
+
+    /// `--goal` is one of `MARATHON_GOALS`
+    pub fn from_options(options: &Options) -> Result<Marathon, String> {
+        let goal: String = options.get_choice(
+            "goal",
+            &MARATHON_GOALS.map(String::from),
+            DEFAULT_MARATHON_GOAL.to_string(),
+        )?;
+        Ok(Marathon::new(goal.parse().ok()))
+    }
+}
+
+impl Mode for Marathon {
+    fn name(&self) -> String {
+        match self.goal {
+            Some(goal) => format!("Marathon {}L", goal),
+            None => "Marathon Endless".to_string(),
+        }
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: &GameEvent) {
+        match event {
+            GameEvent::Lock { cleared, .. } if *cleared > 0 => {
+                game.level = (1 + game.stats.lines / LEVEL_LINES).min(MAX_LEVEL);
+            }
+            _ => {}
+        }
+    }
+
+    fn is_finished(&self, game: &Game) -> bool {
+        self.goal.is_some_and(|goal| game.stats.lines >= goal)
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        let lines = match self.goal {
+            Some(goal) => format!("Lines {}/{}", game.stats.lines.min(goal), goal),
+            None => format!("Lines {}", game.stats.lines),
+        };
+        vec![
+            format!("Score {}", game.score),
+            format!("Level {}", game.level),
+            lines,
+            format!("Time  {}", format_time(game.stats.elapsed)),
+        ]
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Score  {}", game.score),
+            format!("Level  {}", game.level),
+            format!("Lines  {}", game.stats.lines),
+            format!("Pieces {}", game.stats.pieces),
+            format!("Time   {}", format_time(game.stats.elapsed)),
+        ]
+    }
+
+    fn record(&self, game: &Game) -> Option<Record> {
+        Some(Record::new(
+            self.name(),
+            game.score,
+            game.stats.lines,
+            game.level,
+            game.stats.elapsed.as_millis() as u64,
+        ))
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use crate::{
+    core::{Game, GameEvent, Timing},
+    records::Record,
+};
+
+use super::{format_time, Mode};
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+const MAX_LEVEL: u32 = 999;
+const SECTION_LEVELS: u32 = 100;
+
+/// (level, gravity in 1/256 rows per frame), TGM speed curve
+/// 5120 is 20G
+static SPEED_CURVE: [(u32, u32); 30] = [
+    (0, 4),
+    (30, 6),
+    (35, 8),
+    (40, 10),
+    (50, 12),
+    (60, 16),
+    (70, 32),
+    (80, 48),
+    (90, 64),
+    (100, 80),
+    (120, 96),
+    (140, 112),
+    (160, 128),
+    (170, 144),
+    (200, 4),
+    (220, 32),
+    (230, 64),
+    (233, 96),
+    (236, 128),
+    (239, 160),
+    (243, 192),
+    (247, 224),
+    (251, 256),
+    (300, 512),
+    (330, 768),
+    (360, 1024),
+    (400, 1280),
+    (420, 1024),
+    (450, 768),
+    (500, 5120),
+];
+
+/// (ARE, line clear delay, lock delay) in frames for each section
+static SECTION_DELAYS: [(u32, u32, u32); 10] = [
+    (25, 40, 30),
+    (25, 40, 30),
+    (25, 40, 30),
+    (25, 40, 30),
+    (25, 40, 30),
+    (25, 25, 30),
+    (16, 16, 28),
+    (12, 12, 24),
+    (12, 6, 20),
+    (6, 6, 17),
+];
+
+/// (grade, score needed)
+static GRADES: [(&str, u32); 18] = [
+    ("9", 0),
+    ("8", 400),
+    ("7", 800),
+    ("6", 1400),
+    ("5", 2000),
+    ("4", 3500),
+    ("3", 5500),
+    ("2", 8000),
+    ("1", 12000),
+    ("S1", 16000),
+    ("S2", 22000),
+    ("S3", 30000),
+    ("S4", 40000),
+    ("S5", 52000),
+    ("S6", 66000),
+    ("S7", 82000),
+    ("S8", 100000),
+    ("S9", 120000),
+];
+/// time limit to reach level 999 for GM grade
+const GM_TIME: Duration = Duration::from_secs(13 * 60 + 30);
+
+fn frames(count: u32) -> Duration {
+    FRAME * count
+}
+
+/// TGM style mode, level goes up with pieces and lines until 999
+/// speed reaches 20G and delays get shorter every section
+pub struct Master {
+    level: u32,
+    /// TGM score, decides the grade
+    score: u32,
+    /// TGM combo multiplier, 1 when there's no combo
+    combo: u32,
+}
+
+impl Master {
+    pub fn new() -> Master {
+        Master {
+            level: 0,
+            score: 0,
+            combo: 1,
+        }
+    }
+
+    fn timing(&self) -> Timing {
+        let section = (self.level / SECTION_LEVELS) as usize;
+        let (are, line_clear, lock) = SECTION_DELAYS[section.min(SECTION_DELAYS.len() - 1)];
+        let (_, gravity) = SPEED_CURVE
+            .iter()
+            .rev()
+            .find(|(level, _)| *level <= self.level)
+            .unwrap();
+        Timing {
+            are: frames(are),
+            line_clear_delay: frames(line_clear),
+            lock_delay: frames(lock),
+            gravity: Some(FRAME * 256 / *gravity),
+        }
+    }
+
+    fn grade(&self, game: &Game) -> String {
+        let (grade, _) = GRADES
+            .iter()
+            .rev()
+            .find(|(_, score)| *score <= self.score)
+            .unwrap();
+        if self.level >= MAX_LEVEL && *grade == "S9" && game.stats.elapsed <= GM_TIME {
+            "GM".to_string()
+        } else {
+            grade.to_string()
+        }
+    }
+
+    fn next_grade_score(&self) -> Option<u32> {
+        GRADES
+            .iter()
+            .map(|(_, score)| *score)
+            .find(|score| *score > self.score)
+    }
+
+    /// level where next section starts, level stops before it until a line is cleared
+    fn section_stop(&self) -> u32 {
+        ((self.level / SECTION_LEVELS + 1) * SECTION_LEVELS).min(MAX_LEVEL)
+    }
+}
+
+impl Mode for Master {
+    fn name(&self) -> String {
+        "Master".to_string()
+    }
+
+    fn init(&mut self, game: &mut Game) {
+        game.timing = self.timing();
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: &GameEvent) {
+        let GameEvent::Lock {
+            cleared, perfect, ..
+        } = *event;
+        let cleared = cleared as u32;
+        if cleared > 0 {
+            self.combo += 2 * cleared - 2;
+            let bravo = if perfect { 4 } else { 1 };
+            self.score += (self.level + cleared).div_ceil(4) * cleared * self.combo * bravo;
+            self.level = (self.level + cleared).min(MAX_LEVEL);
+        } else {
+            self.combo = 1;
+            if self.level + 1 < self.section_stop() {
+                self.level += 1;
+            }
+        }
+        game.timing = self.timing();
+    }
+
+    fn is_finished(&self, _game: &Game) -> bool {
+        self.level >= MAX_LEVEL
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        let next = match self.next_grade_score() {
+            Some(score) => format!("Next  {}", score),
+            None => String::new(),
+        };
+        vec![
+            format!("Grade {}", self.grade(game)),
+            format!("Score {}", self.score),
+            next,
+            String::new(),
+            format!("Level {:>3}", self.level),
+            format!("      {:>3}", self.section_stop()),
+            String::new(),
+            format!("Time  {}", format_time(game.stats.elapsed)),
+        ]
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Grade  {}", self.grade(game)),
+            format!("Score  {}", self.score),
+            format!("Level  {}", self.level),
+            format!("Lines  {}", game.stats.lines),
+            format!("Time   {}", format_time(game.stats.elapsed)),
+        ]
+    }
+
+    fn record(&self, game: &Game) -> Option<Record> {
+        Some(Record::new(
+            self.name(),
+            self.score as u64,
+            game.stats.lines,
+            self.level,
+            game.stats.elapsed.as_millis() as u64,
+        ))
+    }
+}
@@ -0,0 +1,343 @@
+use std::fs;
+
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+
+use crate::{
+    core::{Board, Cell, Game, GameEvent, Mino, MinoType, GRID_WIDTH},
+    movegen::{placements, sorted_cells},
+    pieces::PieceSet,
+    records::data_dir,
+    rotation::RotationSystem,
+    ControlKind,
+};
+
+use super::{format_time, Mode, Options};
+
+const BUILTIN_SETUPS: &str = include_str!("../../data/openers.json");
+/// player's own setups in data directory, added after built-in ones
+const SETUPS_FILE: &str = "openers.json";
+/// random bag orders tried before giving up
+const BAG_TRIES: usize = 1000;
+
+/// description is wrapped to fit in HUD
+const HUD_WIDTH: usize = 26;
+
+type Cells = Vec<(i8, i8)>;
+
+/// split text into lines no longer than `width` at spaces
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// setup as written in data file
+/// `rows` go from top to bottom, each piece is a group of same letters
+/// and `.` is empty, second piece of same type uses lowercase letter
+#[derive(Deserialize)]
+struct SetupData {
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// order of pieces used with `--bag fixed`
+    queue: String,
+    rows: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Setup {
+    pub name: String,
+    pub description: String,
+    pub queue: Vec<MinoType>,
+    /// target cells of each piece, sorted
+    pub pieces: Vec<(MinoType, Cells)>,
+}
+
+impl Setup {
+    fn from_data(data: SetupData) -> Result<Setup, String> {
+        let invalid = |reason: String| format!("invalid setup {}: {}", data.name, reason);
+        let queue = data
+            .queue
+            .chars()
+            .map(|c| {
+                MinoType::from_char(c.to_ascii_uppercase())
+                    .ok_or_else(|| invalid(format!("unknown piece {}", c)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let height = data.rows.len();
+        let mut letters: Vec<(char, Cells)> = Vec::new();
+        for (row, line) in data.rows.iter().enumerate() {
+            if line.chars().count() != GRID_WIDTH as usize {
+                return Err(invalid(format!(
+                    "row {} isn't {} wide",
+                    row + 1,
+                    GRID_WIDTH
+                )));
+            }
+            for (x, c) in line.chars().enumerate() {
+                if c == '.' {
+                    continue;
+                }
+                let cell = (x as i8, (height - 1 - row) as i8);
+                match letters.iter_mut().find(|(letter, _)| *letter == c) {
+                    Some((_, cells)) => cells.push(cell),
+                    None => letters.push((c, vec![cell])),
+                }
+            }
+        }
+        let mut pieces = Vec::new();
+        for (letter, mut cells) in letters {
+            let mino_type = MinoType::from_char(letter.to_ascii_uppercase())
+                .ok_or_else(|| invalid(format!("unknown piece {}", letter)))?;
+            cells.sort();
+            if cells.len() != 4 {
+                return Err(invalid(format!("piece {} should have 4 cells", letter)));
+            }
+            pieces.push((mino_type, cells));
+        }
+
+        let setup = Setup {
+            name: data.name.clone(),
+            description: data.description,
+            queue,
+            pieces,
+        };
+        if !setup.can_build(&setup.queue) {
+            return Err(invalid(format!("can't be built with queue {}", data.queue)));
+        }
+        Ok(setup)
+    }
+
+    /// true when every piece can be placed using `queue` and hold
+    fn can_build(&self, queue: &[MinoType]) -> bool {
+        let placed = vec![false; self.pieces.len()];
+        self.search(&Board::new(), &placed, queue, None)
+    }
+
+    fn search(
+        &self,
+        board: &Board,
+        placed: &[bool],
+        queue: &[MinoType],
+        hold: Option<MinoType>,
+    ) -> bool {
+        if placed.iter().all(|&placed| placed) {
+            return true;
+        }
+        let Some((&current, rest)) = queue.split_first() else {
+            return false;
+        };
+        // (piece to place, queue after it, hold after it)
+        let mut choices = vec![(current, rest, hold)];
+        match (hold, rest.split_first()) {
+            (Some(held), _) => choices.push((held, rest, Some(current))),
+            (None, Some((&next, rest))) => choices.push((next, rest, Some(current))),
+            (None, None) => {}
+        }
+        for (piece, queue, hold) in choices {
+            for index in self.reachable(board, placed, piece) {
+                let mut board = *board;
+                for &(x, y) in &self.pieces[index].1 {
+                    board.grid[y as usize][x as usize] = Cell::Mino(piece);
+                }
+                let mut placed = placed.to_vec();
+                placed[index] = true;
+                if self.search(&board, &placed, queue, hold) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// unplaced pieces of given type which can be placed on `board`
+    fn reachable(&self, board: &Board, placed: &[bool], mino_type: MinoType) -> Vec<usize> {
+        let spawned = Mino::new(
+            mino_type,
+            RotationSystem::default(),
+            &PieceSet::default(),
+            board,
+        );
+        if spawned.collides(board) {
+            return Vec::new();
+        }
+        let landed: Vec<Cells> = placements(&spawned, board)
+            .iter()
+            .map(sorted_cells)
+            .collect();
+        (0..self.pieces.len())
+            .filter(|&index| {
+                let (piece_type, cells) = &self.pieces[index];
+                !placed[index] && *piece_type == mino_type && landed.contains(cells)
+            })
+            .collect()
+    }
+
+    /// random bags which can build this setup
+    fn random_queue(&self) -> Option<Vec<MinoType>> {
+        let mut rng = rand::thread_rng();
+        (0..BAG_TRIES).find_map(|_| {
+            let mut queue = Vec::new();
+            for _ in 0..2 {
+                let mut bag = MinoType::ALL;
+                bag.shuffle(&mut rng);
+                queue.extend(bag);
+            }
+            self.can_build(&queue).then_some(queue)
+        })
+    }
+}
+
+/// built-in setups followed by setups in data directory
+pub fn load_setups() -> Result<Vec<Setup>, String> {
+    let mut data: Vec<SetupData> = serde_json::from_str(BUILTIN_SETUPS)
+        .map_err(|e| format!("invalid built-in setups: {}", e))?;
+    if let Some(path) = data_dir().map(|dir| dir.join(SETUPS_FILE)) {
+        if path.exists() {
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+            let own: Vec<SetupData> = serde_json::from_str(&text)
+                .map_err(|e| format!("invalid setups in {}: {}", path.display(), e))?;
+            data.extend(own);
+        }
+    }
+    data.into_iter().map(Setup::from_data).collect()
+}
+
+/// build a setup shown as overlay, wrong placements are taken back
+pub struct Opener {
+    setup: Setup,
+    queue: Vec<MinoType>,
+    placed: Vec<bool>,
+    /// indices of placed pieces in order, undo takes back the last one
+    history: Vec<usize>,
+    /// pieces taken back by undo, latest last
+    redo: Vec<usize>,
+    mistakes: u32,
+}
+
+impl Opener {
+    pub fn from_options(options: &Options) -> Result<Opener, String> {
+        let setups = load_setups()?;
+        let names: Vec<String> = setups.iter().map(|setup| setup.name.clone()).collect();
+        let name = options.get_str("opener").unwrap_or(&names[0]);
+        let setup = setups
+            .into_iter()
+            .find(|setup| setup.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("--opener should be one of {}", names.join(", ")))?;
+        let queue = match options.get_str("bag").unwrap_or("fixed") {
+            "fixed" => setup.queue.clone(),
+            "random" => setup
+                .random_queue()
+                .ok_or_else(|| format!("can't find bag order building {}", setup.name))?,
+            _ => return Err("--bag should be one of fixed, random".to_string()),
+        };
+        Ok(Opener {
+            placed: vec![false; setup.pieces.len()],
+            setup,
+            queue,
+            history: Vec::new(),
+            redo: Vec::new(),
+            mistakes: 0,
+        })
+    }
+}
+
+impl Mode for Opener {
+    fn name(&self) -> String {
+        self.setup.name.clone()
+    }
+
+    /// setups are checked to be buildable with SRS
+    fn rotation(&self) -> Option<RotationSystem> {
+        Some(RotationSystem::default())
+    }
+
+    fn init(&mut self, game: &mut Game) {
+        game.load(Board::new(), &self.queue, None);
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: &GameEvent) {
+        let GameEvent::Lock { piece, .. } = event;
+        let cells = sorted_cells(piece);
+        let target = (0..self.setup.pieces.len()).find(|&index| {
+            !self.placed[index] && self.setup.pieces[index] == (piece.mino_type, cells.clone())
+        });
+        match target {
+            Some(index) => {
+                self.placed[index] = true;
+                self.history.push(index);
+                self.redo.clear();
+            }
+            None => {
+                self.mistakes += 1;
+                game.take_back();
+            }
+        }
+    }
+
+    fn on_control(&mut self, _game: &mut Game, control: ControlKind) {
+        match control {
+            ControlKind::Undo => {
+                if let Some(index) = self.history.pop() {
+                    self.placed[index] = false;
+                    self.redo.push(index);
+                }
+            }
+            ControlKind::Redo => {
+                if let Some(index) = self.redo.pop() {
+                    self.placed[index] = true;
+                    self.history.push(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn allows_undo(&self) -> bool {
+        true
+    }
+
+    fn overlay(&self, _game: &Game) -> Vec<(i8, i8, MinoType)> {
+        self.setup
+            .pieces
+            .iter()
+            .zip(&self.placed)
+            .filter(|(_, &placed)| !placed)
+            .flat_map(|((mino_type, cells), _)| cells.iter().map(|&(x, y)| (x, y, *mino_type)))
+            .collect()
+    }
+
+    fn is_finished(&self, _game: &Game) -> bool {
+        self.placed.iter().all(|&placed| placed)
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        let placed = self.placed.iter().filter(|&&placed| placed).count();
+        let mut lines = wrap(&self.setup.description, HUD_WIDTH);
+        lines.extend([
+            String::new(),
+            format!("Pieces   {}/{}", placed, self.setup.pieces.len()),
+            format!("Mistakes {}", self.mistakes),
+            format!("Time     {}", format_time(game.stats.elapsed)),
+        ]);
+        lines
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Time     {}", format_time(game.stats.elapsed)),
+            format!("Mistakes {}", self.mistakes),
+        ]
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use crate::{
+    core::{Game, GameEvent},
+    records::Record,
+};
+
+use super::{format_time, Mode, Options};
+
+pub const SPRINT_GOALS: [u32; 3] = [20, 40, 100];
+pub const DEFAULT_SPRINT_GOAL: u32 = 40;
+/// lines between two splits
+const SPLIT_LINES: u32 = 10;
+
+/// clear `goal` lines as fast as possible
+pub struct Sprint {
+    goal: u32,
+    /// time when every `SPLIT_LINES` lines were cleared
+    splits: Vec<Duration>,
+}
+
+impl Sprint {
+    pub fn new(goal: u32) -> Sprint {
+        Sprint {
+            goal,
+            splits: Vec::new(),
+        }
+    }
+
+    /// `--goal` is one of `SPRINT_GOALS`
+    pub fn from_options(options: &Options) -> Result<Sprint, String> {
+        let goal = options.get_choice("goal", &SPRINT_GOALS, DEFAULT_SPRINT_GOAL)?;
+        Ok(Sprint::new(goal))
+    }
+
+    fn split_lines(&self) -> Vec<String> {
+        let mut previous = Duration::ZERO;
+        self.splits
+            .iter()
+            .enumerate()
+            .map(|(i, &split)| {
+                let line = format!(
+                    "{:>3}L {} (+{})",
+                    (i as u32 + 1) * SPLIT_LINES,
+                    format_time(split),
+                    format_time(split - previous)
+                );
+                previous = split;
+                line
+            })
+            .collect()
+    }
+}
+
+impl Mode for Sprint {
+    fn name(&self) -> String {
+        format!("Sprint {}L", self.goal)
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: &GameEvent) {
+        match event {
+            GameEvent::Lock { cleared, .. } if *cleared > 0 => {
+                let lines = game.stats.lines.min(self.goal);
+                while (self.splits.len() as u32 + 1) * SPLIT_LINES <= lines {
+                    self.splits.push(game.stats.elapsed);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_finished(&self, game: &Game) -> bool {
+        game.stats.lines >= self.goal
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        let mut lines = vec![
+            format!("Time  {}", format_time(game.stats.elapsed)),
+            format!("PPS   {:.2}", game.stats.pps()),
+            format!("Lines {}/{}", game.stats.lines.min(self.goal), self.goal),
+            String::new(),
+        ];
+        lines.extend(self.split_lines());
+        lines
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        let mut lines = if self.is_finished(game) {
+            vec![
+                format!("Time   {}", format_time(game.stats.elapsed)),
+                format!("Pieces {}", game.stats.pieces),
+                format!("PPS    {:.2}", game.stats.pps()),
+            ]
+        } else {
+            vec![format!("Lines  {}/{}", game.stats.lines, self.goal)]
+        };
+        lines.push(String::new());
+        lines.extend(self.split_lines());
+        lines
+    }
+
+    /// only finished sprints are kept
+    fn record(&self, game: &Game) -> Option<Record> {
+        self.is_finished(game).then(|| {
+            Record::new(
+                self.name(),
+                game.score,
+                game.stats.lines,
+                game.level,
+                game.stats.elapsed.as_millis() as u64,
+            )
+        })
+    }
+}
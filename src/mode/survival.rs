@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use crate::{
+    core::{random_hole, Game},
+    records::Record,
+};
+
+use super::{format_time, Mode};
+
+/// time between garbage rows at the start
+const START_INTERVAL: Duration = Duration::from_millis(3000);
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+/// interval gets `SPEED_UP` times shorter every `SPEED_UP_TIME`
+const SPEED_UP: f64 = 0.85;
+const SPEED_UP_TIME: Duration = Duration::from_secs(20);
+
+/// survive as long as possible against garbage rising on a timer
+pub struct Survival {
+    /// game time when next garbage row is queued
+    next_garbage: Duration,
+    last_hole: Option<usize>,
+}
+
+impl Survival {
+    pub fn new() -> Survival {
+        Survival {
+            next_garbage: START_INTERVAL,
+            last_hole: None,
+        }
+    }
+
+    fn interval(elapsed: Duration) -> Duration {
+        let steps = elapsed.as_secs_f64() / SPEED_UP_TIME.as_secs_f64();
+        START_INTERVAL
+            .mul_f64(SPEED_UP.powf(steps))
+            .max(MIN_INTERVAL)
+    }
+}
+
+impl Mode for Survival {
+    fn name(&self) -> String {
+        "Survival".to_string()
+    }
+
+    fn update(&mut self, game: &mut Game) {
+        while game.stats.elapsed >= self.next_garbage {
+            let hole = random_hole(self.last_hole);
+            self.last_hole = Some(hole);
+            game.queue_garbage(&[hole]);
+            self.next_garbage += Survival::interval(self.next_garbage);
+        }
+    }
+
+    fn is_finished(&self, _game: &Game) -> bool {
+        false
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Time     {}", format_time(game.stats.elapsed)),
+            format!("Incoming {}", game.pending_garbage.len()),
+            format!("Cleared  {}", game.stats.garbage_cleared),
+            format!(
+                "Rate     {:.2}s",
+                Survival::interval(game.stats.elapsed).as_secs_f64()
+            ),
+        ]
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Survived {}", format_time(game.stats.elapsed)),
+            format!("Cleared  {}", game.stats.garbage_cleared),
+            format!("Lines    {}", game.stats.lines),
+            format!("Pieces   {}", game.stats.pieces),
+        ]
+    }
+
+    /// time survived is the score
+    fn record(&self, game: &Game) -> Option<Record> {
+        let survived = game.stats.elapsed.as_millis() as u64;
+        Some(Record::new(
+            self.name(),
+            survived,
+            game.stats.lines,
+            game.level,
+            survived,
+        ))
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use crate::{
+    core::{Game, GameEvent},
+    records::Record,
+    score,
+};
+
+use super::{format_time, Mode, Options};
+
+pub const TIMED_MINUTES: [u64; 2] = [2, 3];
+pub const DEFAULT_TIMED_MINUTES: u64 = 2;
+/// Blitz level goes up every this duration
+const BLITZ_LEVEL_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy)]
+pub enum TimedKind {
+    /// level rises with time
+    Blitz,
+    /// level is fixed
+    Ultra,
+}
+
+/// score as much as possible in fixed time
+pub struct Timed {
+    kind: TimedKind,
+    duration: Duration,
+    /// name and score of last clear
+    last_clear: Option<(String, u64)>,
+}
+
+impl Timed {
+    pub fn new(kind: TimedKind, minutes: u64) -> Timed {
+        Timed {
+            kind,
+            duration: Duration::from_secs(minutes * 60),
+            last_clear: None,
+        }
+    }
+
+    /// `--minutes` is one of `TIMED_MINUTES`
+    pub fn from_options(kind: TimedKind, options: &Options) -> Result<Timed, String> {
+        let minutes = options.get_choice("minutes", &TIMED_MINUTES, DEFAULT_TIMED_MINUTES)?;
+        Ok(Timed::new(kind, minutes))
+    }
+}
+
+impl Mode for Timed {
+    fn name(&self) -> String {
+        let minutes = self.duration.as_secs() / 60;
+        match self.kind {
+            TimedKind::Blitz => format!("Blitz {}min", minutes),
+            TimedKind::Ultra => format!("Ultra {}min", minutes),
+        }
+    }
+
+    fn on_event(&mut self, _game: &mut Game, event: &GameEvent) {
+        let GameEvent::Lock {
+            cleared,
+            spin,
+            perfect,
+            score,
+            ..
+        } = *event;
+        if let Some(name) = score::clear_name(cleared, spin, perfect) {
+            self.last_clear = Some((name, score));
+        }
+    }
+
+    fn update(&mut self, game: &mut Game) {
+        if let TimedKind::Blitz = self.kind {
+            let elapsed = game.stats.elapsed.min(self.duration);
+            game.level = 1 + (elapsed.as_millis() / BLITZ_LEVEL_TIME.as_millis()) as u32;
+        }
+    }
+
+    fn is_finished(&self, game: &Game) -> bool {
+        game.stats.elapsed >= self.duration
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        let left = self.duration.saturating_sub(game.stats.elapsed);
+        let mut lines = vec![
+            format!("Time  {}", format_time(left)),
+            format!("Score {}", game.score),
+            format!("Level {}", game.level),
+            format!("Lines {}", game.stats.lines),
+            format!("PPS   {:.2}", game.stats.pps()),
+        ];
+        if let Some((name, score)) = &self.last_clear {
+            lines.extend([String::new(), name.clone(), format!("+{}", score)]);
+        }
+        lines
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Score  {}", game.score),
+            format!("Level  {}", game.level),
+            format!("Lines  {}", game.stats.lines),
+            format!("Pieces {}", game.stats.pieces),
+            format!("PPS    {:.2}", game.stats.pps()),
+            format!(
+                "Time   {}",
+                format_time(game.stats.elapsed.min(self.duration))
+            ),
+        ]
+    }
+
+    fn record(&self, game: &Game) -> Option<Record> {
+        Some(Record::new(
+            self.name(),
+            game.score,
+            game.stats.lines,
+            game.level,
+            game.stats.elapsed.min(self.duration).as_millis() as u64,
+        ))
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use crate::{
+    core::{Board, Cell, Game, GameEvent, Mino, MinoType},
+    pieces, ControlKind,
+};
+
+use super::Mode;
+
+/// no gravity and no top out, board can be edited freely
+pub struct Zen;
+
+/// next item of `list` after `current`, wraps around
+fn cycle<T: PartialEq + Copy>(list: &[T], current: T) -> T {
+    let index = list.iter().position(|&item| item == current).unwrap_or(0);
+    list[(index + 1) % list.len()]
+}
+
+/// clear board instead of topping out
+fn clear_top_out(game: &mut Game) {
+    if game.over {
+        game.board = Board::new();
+        game.over = false;
+        game.set_player(game.player.mino_type);
+    }
+}
+
+impl Mode for Zen {
+    fn name(&self) -> String {
+        "Zen".to_string()
+    }
+
+    fn init(&mut self, game: &mut Game) {
+        game.timing.gravity = Some(Duration::MAX);
+    }
+
+    fn on_event(&mut self, game: &mut Game, _event: &GameEvent) {
+        clear_top_out(game);
+    }
+
+    fn on_control(&mut self, game: &mut Game, control: ControlKind) {
+        let mino_types = pieces::mino_types(&game.pieces);
+        match control {
+            ControlKind::CyclePiece => {
+                game.set_player(cycle(&mino_types, game.player.mino_type));
+            }
+            ControlKind::CycleHold => {
+                let holds: Vec<Option<MinoType>> = std::iter::once(None)
+                    .chain(mino_types.into_iter().map(Some))
+                    .collect();
+                game.set_hold(cycle(&holds, game.hold));
+            }
+            // hold is swapped after this, board is cleared before the piece spawns into it
+            ControlKind::Hold if game.can_hold() => {
+                let incoming = game.hold.or_else(|| game.bags.list.last().copied());
+                let blocked = incoming.is_some_and(|mino_type| {
+                    Mino::new(mino_type, game.rotation, &game.pieces, &game.board)
+                        .collides(&game.board)
+                });
+                if blocked {
+                    game.board = Board::new();
+                }
+            }
+            _ => {}
+        }
+        // changed piece or undone position may not fit
+        clear_top_out(game);
+    }
+
+    fn allows_undo(&self) -> bool {
+        true
+    }
+
+    fn on_paint(&mut self, game: &mut Game, x: i8, y: i8, erase: bool) {
+        let cell = if erase { Cell::Empty } else { Cell::_Garbage };
+        game.set_cell(x, y, cell);
+    }
+
+    fn is_finished(&self, _game: &Game) -> bool {
+        false
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Pieces {}", game.stats.pieces),
+            format!("Lines  {}", game.stats.lines),
+            String::new(),
+            "click paint cell".to_string(),
+            "right click erase".to_string(),
+        ]
+    }
+
+    fn controls(&self) -> Vec<(ControlKind, &'static str)> {
+        vec![
+            (ControlKind::Undo, "undo"),
+            (ControlKind::Redo, "redo"),
+            (ControlKind::CyclePiece, "change piece"),
+            (ControlKind::CycleHold, "change hold"),
+        ]
+    }
+
+    fn results(&self, game: &Game) -> Vec<String> {
+        vec![
+            format!("Pieces {}", game.stats.pieces),
+            format!("Lines  {}", game.stats.lines),
+        ]
+    }
+}
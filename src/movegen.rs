@@ -0,0 +1,231 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    core::{Board, Direction, Mino},
+    ControlKind,
+};
+
+type State = (i8, i8, Direction);
+
+/// inputs used while searching placements
+/// hard drop is not included since it ends the search
+const INPUTS: [ControlKind; 6] = [
+    ControlKind::Left,
+    ControlKind::Right,
+    ControlKind::SoftDrop,
+    ControlKind::Rotate,
+    ControlKind::RotateCC,
+    ControlKind::Rotate180,
+];
+
+fn state(mino: &Mino) -> State {
+    (mino.x, mino.y, mino.direction)
+}
+
+/// cells of `mino` in sorted order, for comparing places
+pub fn sorted_cells(mino: &Mino) -> Vec<(i8, i8)> {
+    let mut cells = mino.get_cells().to_vec();
+    cells.sort();
+    cells
+}
+
+/// apply single input to Mino
+/// returns None when input didn't change anything
+pub fn apply_input(mino: &Mino, input: ControlKind, board: &Board) -> Option<Mino> {
+    let mut next = mino.clone();
+    match input {
+        ControlKind::Left => next.shift(-1, 0, board),
+        ControlKind::Right => next.shift(1, 0, board),
+        ControlKind::SoftDrop => next.shift(0, -1, board),
+        ControlKind::Rotate => next.rotate(Direction::R, board),
+        ControlKind::RotateCC => next.rotate(Direction::L, board),
+        ControlKind::Rotate180 => next.rotate(Direction::D, board),
+        // DAS moves until Mino hits the wall
+        ControlKind::LeftDasStart => {
+            while next.shift(-1, 0, board) {}
+            true
+        }
+        ControlKind::RightDasStart => {
+            while next.shift(1, 0, board) {}
+            true
+        }
+        _ => false,
+    };
+    (state(&next) != state(mino)).then_some(next)
+}
+
+/// find shortest input sequence moving `mino` to the place where `target` lands
+/// cells are compared instead of rotation states, so any equivalent rotation
+/// of O, I, S and Z is accepted
+/// returned sequence always ends with `HardDrop`
+pub fn find_path(mino: &Mino, target: &Mino, board: &Board) -> Option<Vec<ControlKind>> {
+    if mino.mino_type != target.mino_type {
+        return None;
+    }
+    let goal = sorted_cells(&target.get_ghost());
+    let mut visited = HashSet::from([state(mino)]);
+    let mut queue = VecDeque::from([(mino.clone(), Vec::new())]);
+    while let Some((current, path)) = queue.pop_front() {
+        if sorted_cells(&current.get_ghost()) == goal {
+            let mut path = path;
+            path.push(ControlKind::HardDrop);
+            return Some(path);
+        }
+        for input in INPUTS {
+            if let Some(next) = apply_input(&current, input, board) {
+                if visited.insert(state(&next)) {
+                    let mut next_path = path.clone();
+                    next_path.push(input);
+                    queue.push_back((next, next_path));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// inputs counted for finesse
+const FINESSE_INPUTS: [ControlKind; 7] = [
+    ControlKind::Left,
+    ControlKind::Right,
+    ControlKind::LeftDasStart,
+    ControlKind::RightDasStart,
+    ControlKind::Rotate,
+    ControlKind::RotateCC,
+    ControlKind::Rotate180,
+];
+
+/// shortest inputs moving `mino` to where `target` lands without soft drop
+/// None when `target` can't be reached by hard drop
+/// unlike `find_path`, returned sequence doesn't include `HardDrop`
+pub fn finesse_path(mino: &Mino, target: &Mino, board: &Board) -> Option<Vec<ControlKind>> {
+    let goal = sorted_cells(&target.get_ghost());
+    let mut visited = HashSet::from([state(mino)]);
+    let mut queue = VecDeque::from([(mino.clone(), Vec::new())]);
+    while let Some((current, path)) = queue.pop_front() {
+        if sorted_cells(&current.get_ghost()) == goal {
+            return Some(path);
+        }
+        for input in FINESSE_INPUTS {
+            if let Some(next) = apply_input(&current, input, board) {
+                if visited.insert(state(&next)) {
+                    let mut next_path = path.clone();
+                    next_path.push(input);
+                    queue.push_back((next, next_path));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// true when `a` and `b` land on same cells
+pub fn same_place(a: &Mino, b: &Mino) -> bool {
+    sorted_cells(&a.get_ghost()) == sorted_cells(&b.get_ghost())
+}
+
+/// every place where `mino` can land, one Mino for each set of cells
+/// returned Minos are at the bottom
+pub fn placements(mino: &Mino, board: &Board) -> Vec<Mino> {
+    let mut landed = HashSet::new();
+    let mut result = Vec::new();
+    let mut visited = HashSet::from([state(mino)]);
+    let mut queue = VecDeque::from([mino.clone()]);
+    while let Some(current) = queue.pop_front() {
+        let ghost = current.get_ghost();
+        if landed.insert(sorted_cells(&ghost)) {
+            result.push(ghost);
+        }
+        for input in INPUTS {
+            if let Some(next) = apply_input(&current, input, board) {
+                if visited.insert(state(&next)) {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{Cell, MinoType},
+        pieces::PieceSet,
+        rotation::RotationSystem,
+    };
+
+    fn spawn(mino_type: MinoType) -> Mino {
+        Mino::new(
+            mino_type,
+            RotationSystem::default(),
+            &PieceSet::default(),
+            &Board::new(),
+        )
+    }
+
+    /// `mino` turned to `direction` with leftmost cell at `left`, resting on the floor
+    fn target(mino: &Mino, direction: Direction, left: i8) -> Mino {
+        let mut target = mino.clone();
+        target.direction = direction;
+        target.x += left - target.get_cells().iter().map(|&(x, _)| x).min().unwrap();
+        target.y = 5;
+        while target.shift(0, -1, &Board::new()) {}
+        target
+    }
+
+    #[test]
+    fn finesse_path_is_shortest() {
+        use ControlKind::*;
+        let board = Board::new();
+        let t = spawn(MinoType::T);
+        let cases = [
+            (Direction::Z, 3, vec![]),
+            (Direction::Z, 2, vec![Left]),
+            (Direction::Z, 0, vec![LeftDasStart]),
+            (Direction::Z, 7, vec![RightDasStart]),
+            (Direction::R, 4, vec![Rotate]),
+            (Direction::D, 3, vec![Rotate180]),
+            (Direction::L, 0, vec![LeftDasStart, RotateCC]),
+        ];
+        for (direction, left, path) in cases {
+            let target = target(&t, direction, left);
+            assert_eq!(
+                finesse_path(&t, &target, &board),
+                Some(path),
+                "{:?} {}",
+                direction,
+                left
+            );
+        }
+    }
+
+    #[test]
+    fn finesse_path_accepts_equivalent_rotation() {
+        // vertical I at left wall is reached by either rotation
+        let board = Board::new();
+        let i = spawn(MinoType::I);
+        let path = finesse_path(&i, &target(&i, Direction::L, 0), &board).unwrap();
+        assert_eq!(path.len(), 2);
+        assert_eq!(
+            finesse_path(&i, &target(&i, Direction::R, 0), &board),
+            Some(path)
+        );
+    }
+
+    #[test]
+    fn finesse_path_rejects_soft_drop_placement() {
+        // overhang above the target can only be passed under with soft drop
+        let mut board = Board::new();
+        for x in 0..7 {
+            board.grid[2][x] = Cell::_Garbage;
+        }
+        let t = spawn(MinoType::T);
+        let mut tucked = t.clone();
+        tucked.x = 1;
+        tucked.y = 0;
+        assert!(!tucked.collides(&board));
+        assert_eq!(finesse_path(&t, &tucked, &board), None);
+    }
+}
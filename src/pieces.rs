@@ -0,0 +1,228 @@
+//! Piece sets defined as data, for playing with pieces other than tetrominoes
+
+use std::{fs, sync::Arc};
+
+use serde::Deserialize;
+
+use crate::{
+    core::{Direction, MinoType},
+    mode::Options,
+    records::data_dir,
+};
+
+const BUILTIN_SETS: &str = include_str!("../data/pieces.json");
+/// player's own piece sets in data directory, added after built-in ones
+const SETS_FILE: &str = "pieces.json";
+/// the seven tetrominoes, played when no other set is chosen
+pub const TETROMINO_SET: &str = "tetromino";
+
+type Pos = (i8, i8);
+
+/// pieces of the chosen set, indexed by `MinoType::Custom`
+/// empty when playing with tetrominoes
+pub type PieceSet = Arc<[Piece]>;
+
+/// piece as written in data file
+/// `rows` go from top to bottom with `#` for cells and `.` for empty
+/// `center` is counted from bottom-left cell, both coordinates are whole or half
+#[derive(Deserialize)]
+struct PieceData {
+    name: String,
+    rows: Vec<String>,
+    center: (f32, f32),
+    /// SRS style offset table with a row for each direction
+    /// offsets of the set are used when empty
+    #[serde(default)]
+    offsets: Vec<Vec<Pos>>,
+    /// `#rrggbb`
+    color: String,
+}
+
+#[derive(Deserialize)]
+struct SetData {
+    name: String,
+    #[serde(default)]
+    offsets: Vec<Vec<Pos>>,
+    pieces: Vec<PieceData>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Piece {
+    /// cells around Mino position in spawn direction
+    pub cells: Vec<Pos>,
+    /// shift for each direction making cells turn around center
+    /// instead of Mino position
+    state_offsets: [Pos; 4],
+    /// empty when piece doesn't kick
+    offsets: Vec<Vec<Pos>>,
+    /// rows between lowest cell and Mino position
+    pub lift: i8,
+    pub color: (u8, u8, u8),
+}
+
+fn parse_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+impl Piece {
+    fn from_data(data: &PieceData, set_offsets: &[Vec<Pos>]) -> Result<Piece, String> {
+        let (center_x, center_y) = data.center;
+        let is_half = |value: f32| value.fract() != 0.0;
+        if (center_x * 2.0).fract() != 0.0
+            || (center_y * 2.0).fract() != 0.0
+            || is_half(center_x) != is_half(center_y)
+        {
+            return Err(format!(
+                "center of piece {} should be whole or half on both axes",
+                data.name
+            ));
+        }
+        let (origin_x, origin_y) = (center_x.floor() as i8, center_y.floor() as i8);
+
+        let mut cells = Vec::new();
+        for (row, line) in data.rows.iter().rev().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => cells.push((x as i8 - origin_x, row as i8 - origin_y)),
+                    '.' => {}
+                    _ => return Err(format!("unknown cell '{}' in piece {}", c, data.name)),
+                }
+            }
+        }
+        let lift = -cells
+            .iter()
+            .map(|&(_, y)| y)
+            .min()
+            .ok_or_else(|| format!("piece {} has no cells", data.name))?;
+
+        let offsets = if data.offsets.is_empty() {
+            set_offsets.to_vec()
+        } else {
+            data.offsets.clone()
+        };
+        if !offsets.is_empty()
+            && (offsets.len() != 4 || offsets.iter().any(|row| row.len() != offsets[0].len()))
+        {
+            return Err(format!(
+                "offsets of piece {} should be 4 rows of same length",
+                data.name
+            ));
+        }
+
+        let color = parse_color(&data.color)
+            .ok_or_else(|| format!("color of piece {} should be #rrggbb", data.name))?;
+        Ok(Piece {
+            cells,
+            // center half a cell up right of Mino position moves like SRS O
+            state_offsets: if is_half(center_x) {
+                [(0, 0), (0, 1), (1, 1), (1, 0)]
+            } else {
+                [(0, 0); 4]
+            },
+            offsets,
+            lift,
+            color,
+        })
+    }
+
+    /// moves tried in order when rotating from `from` to `to`
+    pub fn kicks(&self, from: Direction, to: Direction) -> Vec<Pos> {
+        let (from_x, from_y) = self.state_offsets[from as usize];
+        let (to_x, to_y) = self.state_offsets[to as usize];
+        let base = (to_x - from_x, to_y - from_y);
+        if self.offsets.is_empty() {
+            return vec![base];
+        }
+        self.offsets[from as usize]
+            .iter()
+            .zip(self.offsets[to as usize].iter())
+            .map(|(pre, post)| (base.0 + pre.0 - post.0, base.1 + pre.1 - post.1))
+            .collect()
+    }
+
+    pub fn state_offset(&self, direction: Direction) -> Pos {
+        self.state_offsets[direction as usize]
+    }
+}
+
+/// built-in piece sets followed by sets in data directory
+fn load_sets() -> Result<Vec<SetData>, String> {
+    let mut sets: Vec<SetData> = serde_json::from_str(BUILTIN_SETS)
+        .map_err(|e| format!("invalid built-in pieces: {}", e))?;
+    if let Some(path) = data_dir().map(|dir| dir.join(SETS_FILE)) {
+        if path.exists() {
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+            let own: Vec<SetData> = serde_json::from_str(&text)
+                .map_err(|e| format!("invalid pieces in {}: {}", path.display(), e))?;
+            sets.extend(own);
+        }
+    }
+    Ok(sets)
+}
+
+/// load piece set given as `--pieces`, `None` when it isn't given
+pub fn from_options(options: &Options) -> Result<Option<PieceSet>, String> {
+    options.get_str("pieces").map(load).transpose()
+}
+
+/// load piece set of the name
+pub fn load(name: &str) -> Result<PieceSet, String> {
+    if name.eq_ignore_ascii_case(TETROMINO_SET) {
+        return Ok(PieceSet::default());
+    }
+    let sets = load_sets()?;
+    let names: Vec<&str> = [TETROMINO_SET]
+        .into_iter()
+        .chain(sets.iter().map(|set| set.name.as_str()))
+        .collect();
+    let set = sets
+        .iter()
+        .find(|set| set.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown piece set {}, sets are {}", name, names.join(", ")))?;
+    if set.pieces.is_empty() || set.pieces.len() > u8::MAX as usize {
+        return Err(format!(
+            "piece set {} should have 1 to 255 pieces",
+            set.name
+        ));
+    }
+    set.pieces
+        .iter()
+        .map(|data| Piece::from_data(data, &set.offsets))
+        .collect()
+}
+
+/// pieces drawn from bags, tetrominoes when `pieces` is empty
+pub fn mino_types(pieces: &[Piece]) -> Vec<MinoType> {
+    if pieces.is_empty() {
+        return MinoType::ALL.to_vec();
+    }
+    (0..pieces.len())
+        .map(|index| MinoType::Custom(index as u8))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Game;
+
+    #[test]
+    fn games_keep_their_own_set() {
+        let tetromino = Game::new();
+        let mut pentomino = Game::new();
+        pentomino.set_pieces(load("pentomino").unwrap());
+        let mut big = Game::new();
+        big.set_pieces(load("big").unwrap());
+        assert_eq!(tetromino.player.get_cells().len(), 4);
+        assert_eq!(pentomino.player.get_cells().len(), 5);
+        assert_eq!(big.player.get_cells().len(), 16);
+    }
+
+    #[test]
+    fn unknown_set_is_rejected() {
+        assert!(load("hexomino").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Local records store, kept as JSON in the XDG data directory
+
+use std::{
+    env, fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+const RECORDS_FILE: &str = "records.json";
+const REPLAYS_FILE: &str = "replays.json";
+/// oldest replays are dropped beyond this many
+const REPLAY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// mode name as shown in game
+    pub mode: String,
+    pub score: u64,
+    pub lines: u32,
+    pub level: u32,
+    pub time_ms: u64,
+    /// seconds since unix epoch
+    pub date: u64,
+}
+
+/// placements of a finished game, each page of fumen is a placed piece
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub mode: String,
+    pub pieces: u32,
+    pub time_ms: u64,
+    /// seconds since unix epoch
+    pub date: u64,
+    pub fumen: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Replays {
+    pub replays: Vec<Replay>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// format seconds since unix epoch as `yyyy-mm-dd` in UTC
+pub fn format_date(date: u64) -> String {
+    // days to civil date, from Howard Hinnant's algorithm
+    let days = (date / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl Record {
+    /// record of current game finished now
+    pub fn new(mode: String, score: u64, lines: u32, level: u32, time_ms: u64) -> Record {
+        Record {
+            mode,
+            score,
+            lines,
+            level,
+            time_ms,
+            date: now(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Records {
+    pub records: Vec<Record>,
+}
+
+/// `$XDG_DATA_HOME/tet-rs` or `~/.local/share/tet-rs`
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("tet-rs"))
+}
+
+fn data_path(file: &str) -> io::Result<PathBuf> {
+    data_dir()
+        .map(|dir| dir.join(file))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "can't find data directory"))
+}
+
+/// read JSON file in data directory, default when it doesn't exist yet
+fn load_file<T: Default + for<'de> Deserialize<'de>>(file: &str) -> io::Result<T> {
+    match fs::read_to_string(data_path(file)?) {
+        Ok(text) => serde_json::from_str(&text).map_err(io::Error::from),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e),
+    }
+}
+
+fn save_file<T: Serialize>(file: &str, value: &T) -> io::Result<()> {
+    let path = data_path(file)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)?)
+}
+
+impl Records {
+    /// load records, empty when nothing is saved yet
+    pub fn load() -> io::Result<Records> {
+        load_file(RECORDS_FILE)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        save_file(RECORDS_FILE, self)
+    }
+
+    /// add record and save it right away
+    pub fn add(record: Record) -> io::Result<()> {
+        let mut records = Records::load()?;
+        records.records.push(record);
+        records.save()
+    }
+}
+
+impl Replay {
+    pub fn new(mode: String, pieces: u32, time_ms: u64, fumen: String) -> Replay {
+        Replay {
+            mode,
+            pieces,
+            time_ms,
+            date: now(),
+            fumen,
+        }
+    }
+}
+
+impl Replays {
+    /// load replays, empty when nothing is saved yet
+    pub fn load() -> io::Result<Replays> {
+        load_file(REPLAYS_FILE)
+    }
+
+    /// add replay and save it right away, keeping the latest ones
+    pub fn add(replay: Replay) -> io::Result<()> {
+        let mut replays = Replays::load()?;
+        replays.replays.push(replay);
+        let excess = replays.replays.len().saturating_sub(REPLAY_LIMIT);
+        replays.replays.drain(..excess);
+        save_file(REPLAYS_FILE, &replays)
+    }
+}
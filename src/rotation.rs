@@ -0,0 +1,489 @@
+//! Rotation systems decide spawn orientation of pieces and how they kick
+
+use std::sync::Arc;
+
+use crate::{
+    config::Rules,
+    core::{Board, Direction, Mino, MinoType},
+    mode::Options,
+    pieces::{Piece, PieceSet},
+};
+
+type Pos = (i8, i8);
+
+static JLSTZ_OFFSETS: [[Pos; 5]; 4] = [
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+static I_OFFSETS: [[Pos; 5]; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, 0), (2, 0)],
+    [(-1, 0), (0, 0), (0, 0), (0, 1), (0, -2)],
+    [(-1, 1), (1, 1), (-2, 1), (1, 0), (-2, 0)],
+    [(0, 1), (0, 1), (0, 1), (0, -1), (0, 2)],
+];
+static O_OFFSETS: [[Pos; 5]; 4] = [
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)], // No further offset data required
+    [(0, -1), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(-1, -1), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(-1, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+];
+
+/// 180 kicks of TETR.IO, indexed by direction before rotation
+static SRS_X_180: [[Pos; 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+];
+/// 180 kicks of NullpoMino, y is flipped to point up
+static NULLPOMINO_180: [[Pos; 12]; 4] = [
+    [
+        (0, 0),
+        (1, 0),
+        (2, 0),
+        (1, -1),
+        (2, -1),
+        (-1, 0),
+        (-2, 0),
+        (-1, -1),
+        (-2, -1),
+        (0, 1),
+        (3, 0),
+        (-3, 0),
+    ],
+    [
+        (0, 0),
+        (0, -1),
+        (0, -2),
+        (-1, -1),
+        (-1, -2),
+        (0, 1),
+        (0, 2),
+        (-1, 1),
+        (-1, 2),
+        (1, 0),
+        (0, -3),
+        (0, 3),
+    ],
+    [
+        (0, 0),
+        (-1, 0),
+        (-2, 0),
+        (-1, 1),
+        (-2, 1),
+        (1, 0),
+        (2, 0),
+        (1, 1),
+        (2, 1),
+        (0, -1),
+        (-3, 0),
+        (3, 0),
+    ],
+    [
+        (0, 0),
+        (0, -1),
+        (0, -2),
+        (1, -1),
+        (1, -2),
+        (0, 1),
+        (0, 2),
+        (1, 1),
+        (1, 2),
+        (-1, 0),
+        (0, -3),
+        (0, 3),
+    ],
+];
+static NULLPOMINO_I_180: [[Pos; 6]; 4] = [
+    [(0, 0), (-1, 0), (-2, 0), (1, 0), (2, 0), (0, -1)],
+    [(0, 0), (0, -1), (0, -2), (0, 1), (0, 2), (-1, 0)],
+    [(0, 0), (1, 0), (2, 0), (-1, 0), (-2, 0), (0, 1)],
+    [(0, 0), (0, -1), (0, -2), (0, 1), (0, 2), (1, 0)],
+];
+
+/// position of Mino when it spawns with no offset
+const SPAWN_X: i8 = 4;
+const SPAWN_Y: i8 = 18;
+
+pub const ROTATION_NAMES: [&str; 5] = ["srs", "srs+", "ars", "nrs", "none"];
+pub const KICKS_180_NAMES: [&str; 2] = ["srs-x", "nullpomino"];
+
+/// kick table used by 180 rotation of SRS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Kicks180 {
+    #[default]
+    SrsX,
+    Nullpomino,
+}
+
+impl Kicks180 {
+    pub fn from_name(name: &str) -> Option<Kicks180> {
+        Some(match name.to_lowercase().as_str() {
+            "srs-x" => Kicks180::SrsX,
+            "nullpomino" => Kicks180::Nullpomino,
+            _ => return None,
+        })
+    }
+
+    /// moves relative to rotation without kick
+    fn tests(self, mino_type: MinoType, from: Direction) -> &'static [Pos] {
+        match (self, mino_type) {
+            (_, MinoType::O) => &[(0, 0)],
+            (Kicks180::SrsX, _) => &SRS_X_180[from as usize],
+            (Kicks180::Nullpomino, MinoType::I) => &NULLPOMINO_I_180[from as usize],
+            (Kicks180::Nullpomino, _) => &NULLPOMINO_180[from as usize],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RotationSystem {
+    /// guideline Super Rotation System
+    Srs(Kicks180),
+    /// SRS with symmetric I kicks, as in TETR.IO
+    SrsPlus(Kicks180),
+    /// Arika Rotation System from TGM
+    Ars,
+    /// Nintendo Rotation System from NES Tetris
+    Nrs,
+    /// SRS shapes without any kicks
+    NoKick,
+}
+
+impl Default for RotationSystem {
+    fn default() -> Self {
+        RotationSystem::Srs(Kicks180::default())
+    }
+}
+
+impl RotationSystem {
+    pub fn from_name(name: &str, kicks_180: Kicks180) -> Option<RotationSystem> {
+        Some(match name.to_lowercase().as_str() {
+            "srs" => RotationSystem::Srs(kicks_180),
+            "srs+" => RotationSystem::SrsPlus(kicks_180),
+            "ars" => RotationSystem::Ars,
+            "nrs" => RotationSystem::Nrs,
+            "none" => RotationSystem::NoKick,
+            _ => return None,
+        })
+    }
+
+    /// rotation system given as `--rotation` and `--kicks-180`, `rules` of config by default
+    pub fn from_options(options: &Options, rules: &Rules) -> Result<RotationSystem, String> {
+        let kicks_180 = options.get_str("kicks-180").unwrap_or(&rules.kicks_180);
+        let kicks_180 = Kicks180::from_name(kicks_180).ok_or_else(|| {
+            format!(
+                "--kicks-180 should be one of {}",
+                KICKS_180_NAMES.join(", ")
+            )
+        })?;
+        let name = options.get_str("rotation").unwrap_or(&rules.rotation);
+        RotationSystem::from_name(name, kicks_180)
+            .ok_or_else(|| format!("--rotation should be one of {}", ROTATION_NAMES.join(", ")))
+    }
+
+    pub fn spawn_direction(self, mino_type: MinoType) -> Direction {
+        use MinoType::*;
+        match (self, mino_type) {
+            (_, MinoType::Custom(_)) => Direction::Z,
+            // flat side up
+            (RotationSystem::Ars, T | J | L | S | Z) => Direction::D,
+            (RotationSystem::Nrs, _) => Direction::D,
+            _ => Direction::Z,
+        }
+    }
+
+    /// Mino of given type at spawn position
+    pub fn spawn(self, mino_type: MinoType, pieces: &PieceSet) -> Mino {
+        let direction = self.spawn_direction(mino_type);
+        let (dx, mut dy) = self.state_offset(mino_type, direction, pieces);
+        if let MinoType::Custom(index) = mino_type {
+            dy += pieces[index as usize].lift;
+        }
+        Mino {
+            mino_type,
+            direction,
+            rotation: self,
+            pieces: Arc::clone(pieces),
+            ghost_y: SPAWN_Y + dy,
+            x: SPAWN_X + dx,
+            y: SPAWN_Y + dy,
+        }
+    }
+
+    /// shift from cells rotated around Mino origin to where this system draws them
+    /// rotation without kick moves Mino by the difference of these
+    fn state_offset(self, mino_type: MinoType, direction: Direction, pieces: &[Piece]) -> Pos {
+        use Direction as D;
+        use MinoType::*;
+        if let Custom(index) = mino_type {
+            return pieces[index as usize].state_offset(direction);
+        }
+        match self {
+            RotationSystem::Srs(_) | RotationSystem::SrsPlus(_) | RotationSystem::NoKick => {
+                let (x, y) = srs_offsets(mino_type)[direction as usize][0];
+                (-x, -y)
+            }
+            // pieces stay at the bottom of their 3x3 box
+            RotationSystem::Ars => match (mino_type, direction) {
+                (T | J | L | S | Z | O, D::Z) => (0, -1),
+                (S, D::R) => (-1, 0),
+                (Z, D::L) => (1, 0),
+                (I, D::R | D::D) | (O, D::D) => (1, 0),
+                (I | O, D::L) => (1, -1),
+                _ => (0, 0),
+            },
+            RotationSystem::Nrs => match (mino_type, direction) {
+                (S | Z, D::Z) => (0, -1),
+                (S | Z, D::L) => (1, 0),
+                (I, D::Z) => (-1, 0),
+                (I, D::R) => (0, 1),
+                (O, D::Z) => (-1, -1),
+                (O, D::R) => (-1, 0),
+                (O, D::L) => (0, -1),
+                _ => (0, 0),
+            },
+        }
+    }
+
+    /// moves tried in order when `mino` rotates to `to`, first one not colliding is used
+    pub fn kicks(self, mino: &Mino, to: Direction, board: &Board) -> Vec<Pos> {
+        let from = mino.direction;
+        // pieces of piece sets have their own kicks
+        if let MinoType::Custom(index) = mino.mino_type {
+            return mino.pieces[index as usize].kicks(from, to);
+        }
+        let (from_x, from_y) = self.state_offset(mino.mino_type, from, &mino.pieces);
+        let (to_x, to_y) = self.state_offset(mino.mino_type, to, &mino.pieces);
+        let base = (to_x - from_x, to_y - from_y);
+        match self {
+            RotationSystem::Srs(kicks_180) | RotationSystem::SrsPlus(kicks_180)
+                if to == from + Direction::D =>
+            {
+                kicks_180
+                    .tests(mino.mino_type, from)
+                    .iter()
+                    .map(|(x, y)| (base.0 + x, base.1 + y))
+                    .collect()
+            }
+            RotationSystem::Srs(_) => srs_kicks(mino.mino_type, from, to),
+            RotationSystem::SrsPlus(_) => match srs_plus_i_kicks(mino.mino_type, from, to) {
+                Some(kicks) => [(0, 0)]
+                    .into_iter()
+                    .chain(kicks)
+                    .map(|(x, y)| (base.0 + x, base.1 + y))
+                    .collect(),
+                None => srs_kicks(mino.mino_type, from, to),
+            },
+            RotationSystem::Ars => {
+                let mut rotated = mino.clone();
+                rotated.direction = to;
+                rotated.x += base.0;
+                rotated.y += base.1;
+                let center_x = rotated.x - to_x;
+                let center_rule = matches!(mino.mino_type, MinoType::J | MinoType::L | MinoType::T);
+                if matches!(mino.mino_type, MinoType::I | MinoType::O)
+                    || (center_rule && blocked_at_center(&rotated, center_x, board))
+                {
+                    vec![base]
+                } else {
+                    vec![base, (base.0 + 1, base.1), (base.0 - 1, base.1)]
+                }
+            }
+            RotationSystem::Nrs | RotationSystem::NoKick => vec![base],
+        }
+    }
+}
+
+fn srs_offsets(mino_type: MinoType) -> &'static [[Pos; 5]; 4] {
+    match mino_type {
+        MinoType::I => &I_OFFSETS,
+        MinoType::O => &O_OFFSETS,
+        _ => &JLSTZ_OFFSETS,
+    }
+}
+
+fn srs_kicks(mino_type: MinoType, from: Direction, to: Direction) -> Vec<Pos> {
+    let offsets = srs_offsets(mino_type);
+    let pre_offset = offsets[from as usize];
+    let post_offset = offsets[to as usize];
+    pre_offset
+        .iter()
+        .zip(post_offset.iter())
+        .map(|(pre, post)| (pre.0 - post.0, pre.1 - post.1))
+        .collect()
+}
+
+/// I kicks of SRS+ after the first test, relative to rotation without kick
+/// kicks to left and right are mirrored, unlike SRS
+fn srs_plus_i_kicks(mino_type: MinoType, from: Direction, to: Direction) -> Option<[Pos; 4]> {
+    use Direction::*;
+    if mino_type != MinoType::I {
+        return None;
+    }
+    Some(match (from, to) {
+        (Z, R) => [(1, 0), (-2, 0), (-2, -1), (1, 2)],
+        (R, Z) => [(-1, 0), (2, 0), (-1, -2), (2, 1)],
+        (R, D) => [(-1, 0), (2, 0), (-1, 2), (2, -1)],
+        (D, R) => [(-2, 0), (1, 0), (-2, 1), (1, -2)],
+        (D, L) => [(2, 0), (-1, 0), (2, 1), (-1, -2)],
+        (L, D) => [(1, 0), (-2, 0), (1, 2), (-2, -1)],
+        (L, Z) => [(1, 0), (-2, 0), (1, -2), (-2, 1)],
+        (Z, L) => [(-1, 0), (2, 0), (2, -1), (-1, 2)],
+        _ => return None,
+    })
+}
+
+/// ARS center column rule: J, L and T don't kick when the first blocked cell,
+/// read from top-left, is in the center column
+fn blocked_at_center(rotated: &Mino, center_x: i8, board: &Board) -> bool {
+    rotated
+        .get_cells()
+        .into_iter()
+        .filter(|&(x, y)| !board.is_empty(x, y))
+        .min_by_key(|&(x, y)| (-y, x))
+        .is_some_and(|(x, _)| x == center_x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Cell;
+
+    /// kicks of rotating `mino_type` on empty board, relative to rotation without kick
+    fn relative_kicks(
+        rotation: RotationSystem,
+        mino_type: MinoType,
+        from: Direction,
+        to: Direction,
+    ) -> Vec<Pos> {
+        let mut mino = rotation.spawn(mino_type, &PieceSet::default());
+        mino.direction = from;
+        let kicks = rotation.kicks(&mino, to, &Board::new());
+        kicks
+            .iter()
+            .map(|&(x, y)| (x - kicks[0].0, y - kicks[0].1))
+            .collect()
+    }
+
+    #[test]
+    fn srs_plus_i_kicks_are_symmetric() {
+        use Direction::*;
+        // TETR.IO SRS+ I table
+        let cases = [
+            (Z, R, [(0, 0), (1, 0), (-2, 0), (-2, -1), (1, 2)]),
+            (R, Z, [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)]),
+            (R, D, [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]),
+            (D, R, [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]),
+            (D, L, [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)]),
+            (L, D, [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)]),
+            (L, Z, [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)]),
+            (Z, L, [(0, 0), (-1, 0), (2, 0), (2, -1), (-1, 2)]),
+        ];
+        let rotation = RotationSystem::SrsPlus(Kicks180::default());
+        for (from, to, kicks) in cases {
+            assert_eq!(
+                relative_kicks(rotation, MinoType::I, from, to),
+                kicks,
+                "{:?} to {:?}",
+                from,
+                to
+            );
+        }
+
+        // rotation without kick stays in the 4x4 box, third column
+        let mut mino = rotation.spawn(MinoType::I, &PieceSet::default());
+        let left = mino.get_cells().iter().map(|&(x, _)| x).min().unwrap();
+        assert!(mino.rotate(R, &Board::new()));
+        assert!(mino.get_cells().iter().all(|&(x, _)| x == left + 2));
+    }
+
+    #[test]
+    fn srs_x_180_kicks() {
+        use Direction::*;
+        let cases = [
+            (Z, [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)]),
+            (R, [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)]),
+            (D, [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)]),
+            (L, [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)]),
+        ];
+        let rotation = RotationSystem::Srs(Kicks180::SrsX);
+        for (from, kicks) in cases {
+            assert_eq!(
+                relative_kicks(rotation, MinoType::T, from, from + D),
+                kicks,
+                "{:?}",
+                from
+            );
+        }
+    }
+
+    #[test]
+    fn nullpomino_180_kicks() {
+        use Direction::*;
+        // first tests of NullpoMino tables, with y pointing up
+        let cases = [
+            (
+                MinoType::T,
+                Z,
+                vec![(0, 0), (1, 0), (2, 0), (1, -1), (2, -1)],
+            ),
+            (
+                MinoType::T,
+                R,
+                vec![(0, 0), (0, -1), (0, -2), (-1, -1), (-1, -2)],
+            ),
+            (
+                MinoType::T,
+                D,
+                vec![(0, 0), (-1, 0), (-2, 0), (-1, 1), (-2, 1)],
+            ),
+            (
+                MinoType::T,
+                L,
+                vec![(0, 0), (0, -1), (0, -2), (1, -1), (1, -2)],
+            ),
+            (
+                MinoType::I,
+                Z,
+                vec![(0, 0), (-1, 0), (-2, 0), (1, 0), (2, 0)],
+            ),
+            (
+                MinoType::I,
+                R,
+                vec![(0, 0), (0, -1), (0, -2), (0, 1), (0, 2)],
+            ),
+        ];
+        let rotation = RotationSystem::Srs(Kicks180::Nullpomino);
+        for (mino_type, from, kicks) in cases {
+            let relative = relative_kicks(rotation, mino_type, from, from + D);
+            assert_eq!(relative[..kicks.len()], kicks, "{:?} {:?}", mino_type, from);
+        }
+        assert_eq!(relative_kicks(rotation, MinoType::T, Z, D).len(), 12);
+        assert_eq!(relative_kicks(rotation, MinoType::I, Z, D).len(), 6);
+    }
+
+    #[test]
+    fn ars_center_column_blocks_kick() {
+        // T spawns pointing down with center column x = 4, rotating
+        // clockwise puts cells at (4, 17..=19) and (3, 18)
+        let rotation = RotationSystem::Ars;
+        let spawned = rotation.spawn(MinoType::T, &PieceSet::default());
+        assert_eq!((spawned.x, spawned.y), (4, 18));
+
+        let mut board = Board::new();
+        board.grid[19][4] = Cell::_Garbage;
+        assert_eq!(rotation.kicks(&spawned, Direction::L, &board).len(), 1);
+        let mut mino = spawned.clone();
+        assert!(!mino.rotate(Direction::R, &board));
+
+        let mut board = Board::new();
+        board.grid[18][3] = Cell::_Garbage;
+        assert_eq!(rotation.kicks(&spawned, Direction::L, &board).len(), 3);
+        let mut mino = spawned.clone();
+        assert!(mino.rotate(Direction::R, &board));
+        assert_eq!(mino.x, 5);
+    }
+}
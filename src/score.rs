@@ -0,0 +1,135 @@
+//! Guideline scoring
+
+use crate::core::{Board, Direction, Mino, MinoType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// check T-Spin with 3-corner rule
+/// `rotated` should be true when last successful move was rotation
+pub fn detect_spin(player: &Mino, board: &Board, rotated: bool) -> Spin {
+    if !rotated || !matches!(player.mino_type, MinoType::T) {
+        return Spin::None;
+    }
+    let (x, y) = (player.x, player.y);
+    let filled = |(dx, dy): (i8, i8)| !board.is_empty(x + dx, y + dy);
+    let corners = [(-1, 1), (1, 1), (1, -1), (-1, -1)];
+    if corners.into_iter().filter(|&c| filled(c)).count() < 3 {
+        return Spin::None;
+    }
+    let front = match player.direction {
+        Direction::Z => [(-1, 1), (1, 1)],
+        Direction::R => [(1, 1), (1, -1)],
+        Direction::D => [(1, -1), (-1, -1)],
+        Direction::L => [(-1, -1), (-1, 1)],
+    };
+    if front.into_iter().all(filled) {
+        Spin::Full
+    } else {
+        Spin::Mini
+    }
+}
+
+/// true when clear keeps back-to-back chain
+pub fn is_difficult(cleared: u8, spin: Spin) -> bool {
+    cleared >= 4 || (cleared > 0 && spin != Spin::None)
+}
+
+/// base score of a lock before level multiplier
+/// `combo` is number of consecutive line clears including this one
+pub fn lock_score(cleared: u8, spin: Spin, back_to_back: bool, combo: u32, perfect: bool) -> u64 {
+    let base = match (spin, cleared) {
+        (Spin::None, 0) => 0,
+        (Spin::None, 1) => 100,
+        (Spin::None, 2) => 300,
+        (Spin::None, 3) => 500,
+        (Spin::None, _) => 800,
+        (Spin::Mini, 0) => 100,
+        (Spin::Mini, 1) => 200,
+        (Spin::Mini, _) => 400,
+        (Spin::Full, 0) => 400,
+        (Spin::Full, 1) => 800,
+        (Spin::Full, 2) => 1200,
+        (Spin::Full, _) => 1600,
+    };
+    let mut score = if back_to_back && is_difficult(cleared, spin) {
+        base * 3 / 2
+    } else {
+        base
+    };
+    if combo > 1 {
+        score += 50 * (combo as u64 - 1);
+    }
+    if perfect {
+        score += match cleared {
+            1 => 800,
+            2 => 1200,
+            3 => 1800,
+            _ if back_to_back => 3200,
+            _ => 2000,
+        };
+    }
+    score
+}
+
+/// extra attack for combo, indexed by number of consecutive clears
+static COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+/// garbage lines sent by a lock
+pub fn attack(cleared: u8, spin: Spin, back_to_back: bool, combo: u32, perfect: bool) -> u32 {
+    if cleared == 0 {
+        return 0;
+    }
+    let base = match (spin, cleared) {
+        (Spin::None, 1) => 0,
+        (Spin::None, 2) => 1,
+        (Spin::None, 3) => 2,
+        (Spin::None, _) => 4,
+        (Spin::Mini, 1) => 0,
+        (Spin::Mini, _) => 1,
+        (Spin::Full, 1) => 2,
+        (Spin::Full, 2) => 4,
+        (Spin::Full, _) => 6,
+    };
+    let mut attack = base;
+    if back_to_back && is_difficult(cleared, spin) {
+        attack += 1;
+    }
+    let combo_index = (combo as usize).min(COMBO_ATTACK.len() - 1);
+    attack += COMBO_ATTACK[combo_index];
+    if perfect {
+        attack += 10;
+    }
+    attack
+}
+
+/// name of the clear shown to player, e.g. "T-Spin Double"
+pub fn clear_name(cleared: u8, spin: Spin, perfect: bool) -> Option<String> {
+    let lines = match cleared {
+        0 => None,
+        1 => Some("Single"),
+        2 => Some("Double"),
+        3 => Some("Triple"),
+        _ => Some("Tetris"),
+    };
+    let spin = match spin {
+        Spin::None => None,
+        Spin::Mini => Some("T-Spin Mini"),
+        Spin::Full => Some("T-Spin"),
+    };
+    let name = match (spin, lines) {
+        (None, None) => return None,
+        (Some(spin), None) => spin.to_string(),
+        (None, Some(lines)) => lines.to_string(),
+        (Some(spin), Some(lines)) => format!("{} {}", spin, lines),
+    };
+    if perfect {
+        Some(format!("{} PC", name))
+    } else {
+        Some(name)
+    }
+}
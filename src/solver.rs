@@ -0,0 +1,473 @@
+//! Perfect clear solver
+
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+
+use crate::{
+    core::{Board, Cell, Mino, MinoType, GRID_HEIGHT, GRID_WIDTH},
+    mode::Options,
+    movegen::placements,
+    pieces::PieceSet,
+    rotation::RotationSystem,
+};
+
+/// search stops after finding this many solutions
+pub const MAX_SOLUTIONS: usize = 1000;
+
+/// board as bits, cheap to compare and hash while searching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BitBoard {
+    rows: [u16; GRID_HEIGHT as usize],
+}
+
+impl BitBoard {
+    fn from_board(board: &Board) -> BitBoard {
+        let mut rows = [0; GRID_HEIGHT as usize];
+        for (bits, row) in rows.iter_mut().zip(board.grid.iter()) {
+            for (x, cell) in row.iter().enumerate() {
+                if !cell.is_empty() {
+                    *bits |= 1 << x;
+                }
+            }
+        }
+        BitBoard { rows }
+    }
+
+    fn is_filled(&self, x: usize, y: usize) -> bool {
+        self.rows[y] & (1 << x) != 0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&row| row == 0)
+    }
+
+    /// number of empty cells below `height`, None when cells above it are filled
+    fn empty_cells(&self, height: usize) -> Option<usize> {
+        if self.rows[height..].iter().any(|&row| row != 0) {
+            return None;
+        }
+        let filled: u32 = self.rows[..height].iter().map(|row| row.count_ones()).sum();
+        Some(height * GRID_WIDTH as usize - filled as usize)
+    }
+
+    /// false when an empty area below `height` can't be filled with tetrominoes
+    fn is_fillable(&self, height: usize) -> bool {
+        let width = GRID_WIDTH as usize;
+        let mut visited = *self;
+        for start in 0..height * width {
+            if visited.is_filled(start % width, start / width) {
+                continue;
+            }
+            let mut size = 0;
+            let mut stack = vec![(start % width, start / width)];
+            visited.rows[start / width] |= 1 << (start % width);
+            while let Some((x, y)) = stack.pop() {
+                size += 1;
+                let neighbors = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbors {
+                    if nx < width && ny < height && !visited.is_filled(nx, ny) {
+                        visited.rows[ny] |= 1 << nx;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            if size % 4 != 0 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// one placement of a solution
+#[derive(Debug, Clone)]
+pub struct Step {
+    /// board before placing `piece`
+    pub board: Board,
+    pub piece: Mino,
+    /// true when hold is used to get `piece`
+    pub hold: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub steps: Vec<Step>,
+}
+
+/// solver options given as `--pc-height` and `--pc-all`
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    /// perfect clear should be done within this many lines
+    pub height: usize,
+    /// find all solutions instead of the first one
+    pub all: bool,
+}
+
+impl Settings {
+    pub fn from_options(options: &Options) -> Result<Settings, String> {
+        let height = options.get("pc-height")?.unwrap_or(4);
+        if !(1..=GRID_HEIGHT as usize - 4).contains(&height) {
+            return Err(format!(
+                "--pc-height should be between 1 and {}",
+                GRID_HEIGHT - 4
+            ));
+        }
+        Ok(Settings {
+            height,
+            all: options.flag("pc-all"),
+        })
+    }
+}
+
+struct Solver {
+    settings: Settings,
+    rotation: RotationSystem,
+    /// current piece followed by next pieces
+    queue: Vec<MinoType>,
+    /// states known to have no solution
+    failed: HashSet<(BitBoard, usize, Option<MinoType>, usize)>,
+    steps: Vec<Step>,
+    solutions: Vec<Solution>,
+    /// search stops when set
+    cancel: Arc<AtomicBool>,
+}
+
+impl Solver {
+    fn is_done(&self) -> bool {
+        let limit = if self.settings.all { MAX_SOLUTIONS } else { 1 };
+        self.solutions.len() >= limit || self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// returns true when any solution is found from this state
+    /// `index` is position of the current piece in queue
+    fn search(
+        &mut self,
+        board: Board,
+        index: usize,
+        hold: Option<MinoType>,
+        height: usize,
+    ) -> bool {
+        let bits = BitBoard::from_board(&board);
+        let key = (bits, index, hold, height);
+        if self.failed.contains(&key) || self.cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+        let Some(current) = self.queue.get(index).copied() else {
+            return false;
+        };
+        let Some(empty) = bits.empty_cells(height) else {
+            return false;
+        };
+        let available = self.queue.len() - index + hold.is_some() as usize;
+        if empty % 4 != 0 || empty / 4 > available {
+            self.failed.insert(key);
+            return false;
+        }
+
+        // (piece, index of next current piece, hold after placing, hold used)
+        let mut choices = vec![(current, index + 1, hold, false)];
+        match hold {
+            Some(held) if held != current => choices.push((held, index + 1, Some(current), true)),
+            None => {
+                if let Some(&next) = self.queue.get(index + 1) {
+                    if next != current {
+                        choices.push((next, index + 2, Some(current), true));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let mut found = false;
+        for (piece, next_index, next_hold, used_hold) in choices {
+            let mut spawned = Mino::new(piece, self.rotation, &PieceSet::default(), &board);
+            if spawned.collides(&board) {
+                continue;
+            }
+            // rows above `height` are empty, so start searching right above them
+            let lowest = height as i8 + 2;
+            if spawned.y > lowest {
+                spawned.shift(0, lowest - spawned.y, &board);
+            }
+            for placement in placements(&spawned, &board) {
+                let cells = placement.get_cells();
+                if cells.iter().any(|&(_, y)| y as usize >= height) {
+                    continue;
+                }
+                let mut next_board = board;
+                for (x, y) in cells {
+                    next_board.grid[y as usize][x as usize] = Cell::Mino(piece);
+                }
+                let rows = next_board.full_rows();
+                next_board.remove_rows(&rows);
+                let next_height = height - rows.len();
+                let next_bits = BitBoard::from_board(&next_board);
+                if !next_bits.is_fillable(next_height) {
+                    continue;
+                }
+
+                self.steps.push(Step {
+                    board,
+                    piece: placement,
+                    hold: used_hold,
+                });
+                if next_bits.is_empty() {
+                    self.solutions.push(Solution {
+                        steps: self.steps.clone(),
+                    });
+                    found = true;
+                } else if self.search(next_board, next_index, next_hold, next_height) {
+                    found = true;
+                }
+                self.steps.pop();
+                if self.is_done() {
+                    return found;
+                }
+            }
+        }
+        if !found {
+            self.failed.insert(key);
+        }
+        found
+    }
+}
+
+/// find perfect clears using `current`, `hold` and `next` pieces
+/// only tetrominoes are supported, pieces of other sets find nothing
+/// setting `cancel` stops search with solutions found so far
+pub fn solve(
+    board: &Board,
+    current: MinoType,
+    hold: Option<MinoType>,
+    next: &[MinoType],
+    rotation: RotationSystem,
+    settings: Settings,
+    cancel: Arc<AtomicBool>,
+) -> Vec<Solution> {
+    let mut solver = Solver {
+        settings,
+        rotation,
+        queue: [current].into_iter().chain(next.iter().copied()).collect(),
+        failed: HashSet::new(),
+        steps: Vec::new(),
+        solutions: Vec::new(),
+        cancel,
+    };
+    let tetrominoes = hold.iter().chain(&solver.queue).all(MinoType::is_tetromino);
+    if !tetrominoes {
+        return Vec::new();
+    }
+    solver.search(*board, 0, hold, settings.height);
+    solver.solutions
+}
+
+/// steps through solutions found in background
+pub struct Browser {
+    rx: Option<Receiver<Vec<Solution>>>,
+    /// stops solver thread when browser is dropped
+    cancel: Arc<AtomicBool>,
+    /// board solutions start from
+    board: BitBoard,
+    pub solutions: Vec<Solution>,
+    pub solution: usize,
+    pub step: usize,
+}
+
+impl Browser {
+    /// start solving in another thread
+    pub fn spawn(
+        board: Board,
+        current: MinoType,
+        hold: Option<MinoType>,
+        next: Vec<MinoType>,
+        rotation: RotationSystem,
+        settings: Settings,
+    ) -> Browser {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let solver_cancel = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let solutions = solve(
+                &board,
+                current,
+                hold,
+                &next,
+                rotation,
+                settings,
+                solver_cancel,
+            );
+            let _ = tx.send(solutions);
+        });
+        Browser {
+            rx: Some(rx),
+            cancel,
+            board: BitBoard::from_board(&board),
+            solutions: Vec::new(),
+            solution: 0,
+            step: 0,
+        }
+    }
+
+    /// receive solutions when solver is done
+    pub fn poll(&mut self) {
+        if let Some(solutions) = self.rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.solutions = solutions;
+            self.rx = None;
+        }
+    }
+
+    /// false when `board` changed since solving started
+    pub fn is_for(&self, board: &Board) -> bool {
+        BitBoard::from_board(board) == self.board
+    }
+
+    pub fn current(&self) -> Option<&Step> {
+        self.solutions.get(self.solution)?.steps.get(self.step)
+    }
+
+    pub fn title(&self) -> String {
+        if self.rx.is_some() {
+            return "PC solving...".to_string();
+        }
+        match self.solutions.get(self.solution) {
+            Some(solution) => format!(
+                "PC {}/{} step {}/{}{}",
+                self.solution + 1,
+                self.solutions.len(),
+                self.step + 1,
+                solution.steps.len(),
+                if solution.steps[self.step].hold {
+                    " hold"
+                } else {
+                    ""
+                }
+            ),
+            None => "PC no solution".to_string(),
+        }
+    }
+
+    /// `,` `.` move between steps, `<` `>` between solutions
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind == KeyEventKind::Release || self.solutions.is_empty() {
+            return;
+        }
+        let steps = self.solutions[self.solution].steps.len();
+        match key.code {
+            KeyCode::Char(',') => self.step = self.step.saturating_sub(1),
+            KeyCode::Char('.') => self.step = (self.step + 1).min(steps - 1),
+            KeyCode::Char('<') => {
+                self.solution = self.solution.saturating_sub(1);
+                self.step = 0;
+            }
+            KeyCode::Char('>') => {
+                self.solution = (self.solution + 1).min(self.solutions.len() - 1);
+                self.step = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for Browser {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4 rows with left 6 columns filled
+    fn well_board() -> Board {
+        let mut board = Board::new();
+        for row in board.grid.iter_mut().take(4) {
+            for cell in row.iter_mut().take(6) {
+                *cell = Cell::_Garbage;
+            }
+        }
+        board
+    }
+
+    fn solve_all(board: &Board, queue: &[MinoType]) -> Vec<Solution> {
+        let settings = Settings {
+            height: 4,
+            all: true,
+        };
+        solve(
+            board,
+            queue[0],
+            None,
+            &queue[1..],
+            RotationSystem::default(),
+            settings,
+            Arc::new(AtomicBool::new(false)),
+        )
+    }
+
+    /// placing every step in order leaves empty board
+    fn is_perfect_clear(board: &Board, solution: &Solution) -> bool {
+        let mut board = *board;
+        for step in &solution.steps {
+            if BitBoard::from_board(&step.board) != BitBoard::from_board(&board) {
+                return false;
+            }
+            for (x, y) in step.piece.get_cells() {
+                if !board.is_empty(x, y) {
+                    return false;
+                }
+                board.grid[y as usize][x as usize] = Cell::Mino(step.piece.mino_type);
+            }
+            let rows = board.full_rows();
+            board.remove_rows(&rows);
+        }
+        BitBoard::from_board(&board).is_empty()
+    }
+
+    #[test]
+    fn solves_four_line_clear() {
+        use MinoType::*;
+        let board = well_board();
+        let solutions = solve_all(&board, &[O, O, J, J]);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            assert_eq!(solution.steps.len(), 4);
+            assert!(is_perfect_clear(&board, solution));
+        }
+    }
+
+    #[test]
+    fn rejects_unsolvable_queue() {
+        // S pieces can't fill a rectangle
+        use MinoType::*;
+        assert!(solve_all(&well_board(), &[S, S, S, S, S]).is_empty());
+    }
+
+    #[test]
+    fn holds_piece_that_doesnt_fit() {
+        // S leaves odd number of cells in some row, so it has to be held
+        use MinoType::*;
+        let board = well_board();
+        let solutions = solve_all(&board, &[S, O, O, O, O]);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            assert!(solution.steps[0].hold);
+            assert!(solution.steps.iter().all(|step| step.piece.mino_type == O));
+            assert!(is_perfect_clear(&board, solution));
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! Frontend for the Tetris Bot Protocol
+//! https://github.com/tetris-bot-protocol/tbp-spec
+//!
+//! The bot runs as a child process and talks JSON, one message per line,
+//! over its stdin/stdout.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{Board, Cell, Direction, Game, Mino, MinoType, GRID_WIDTH},
+    movegen::find_path,
+    ControlKind,
+};
+
+/// TBP boards are always 40 rows high
+const TBP_BOARD_HEIGHT: usize = 40;
+
+type TbpBoard = Vec<[Option<char>; GRID_WIDTH as usize]>;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FrontendMessage {
+    Rules {},
+    Start {
+        hold: Option<MinoType>,
+        queue: Vec<MinoType>,
+        combo: u32,
+        back_to_back: bool,
+        board: TbpBoard,
+    },
+    Stop,
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece {
+        piece: MinoType,
+    },
+    Quit,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Move {
+    location: PieceLocation,
+    spin: Spin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PieceLocation {
+    #[serde(rename = "type")]
+    kind: MinoType,
+    orientation: Orientation,
+    x: i8,
+    y: i8,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl From<Orientation> for Direction {
+    fn from(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::North => Direction::Z,
+            Orientation::East => Direction::R,
+            Orientation::South => Direction::D,
+            Orientation::West => Direction::L,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Debug)]
+enum State {
+    WaitInfo,
+    WaitReady,
+    /// bot is in sync with the game and waiting for `suggest`
+    Idle,
+    WaitSuggestion,
+    /// bot was stopped and should be started again from current game
+    Restart,
+    Dead(String),
+}
+
+pub struct Bot {
+    pub name: Option<String>,
+    child: Child,
+    stdin: ChildStdin,
+    rx: Receiver<BotMessage>,
+    state: State,
+    /// pieces bot knows about, first one is the current piece
+    queue: Vec<MinoType>,
+    hold: Option<MinoType>,
+    /// board as bot sees it after its last move
+    board: TbpBoard,
+}
+
+impl Bot {
+    /// launch bot process with given command line
+    pub fn spawn(command: &[String]) -> io::Result<Bot> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty bot command"))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // reader thread
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if let Ok(message) = serde_json::from_str::<BotMessage>(&line) {
+                    if tx.send(message).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Bot {
+            name: None,
+            child,
+            stdin,
+            rx,
+            state: State::WaitInfo,
+            queue: Vec::new(),
+            hold: None,
+            board: Vec::new(),
+        })
+    }
+
+    /// reason why bot stopped playing
+    pub fn error(&self) -> Option<&str> {
+        match &self.state {
+            State::Dead(reason) => Some(reason),
+            _ => None,
+        }
+    }
+
+    fn send(&mut self, message: &FrontendMessage) {
+        let result = serde_json::to_writer(&mut self.stdin, message)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(self.stdin))
+            .and_then(|_| self.stdin.flush());
+        if let Err(e) = result {
+            self.state = State::Dead(format!("can't write to bot: {}", e));
+        }
+    }
+
+    /// progress protocol and returns inputs when bot decided where to place
+    /// current piece
+    pub fn poll(&mut self, game: &Game) -> Option<Vec<ControlKind>> {
+        if !game.is_active() {
+            return None;
+        }
+        match self.state {
+            State::Idle if !self.in_sync(game) => {
+                self.restart(game);
+                return None;
+            }
+            State::Idle => {
+                self.send_new_pieces(game);
+                self.send(&FrontendMessage::Suggest);
+                self.state = State::WaitSuggestion;
+                return None;
+            }
+            State::Restart => {
+                self.start(game);
+                return None;
+            }
+            State::Dead(_) => return None,
+            _ => {}
+        }
+        let message = match self.rx.try_recv() {
+            Ok(message) => message,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => {
+                self.state = State::Dead("bot exited".to_string());
+                return None;
+            }
+        };
+        match (&self.state, message) {
+            (_, BotMessage::Error { reason }) => {
+                self.state = State::Dead(reason);
+            }
+            (
+                State::WaitInfo,
+                BotMessage::Info {
+                    name,
+                    version,
+                    author,
+                },
+            ) => {
+                self.name = Some(format!("{} {} by {}", name, version, author));
+                self.send(&FrontendMessage::Rules {});
+                self.state = State::WaitReady;
+            }
+            (State::WaitReady, BotMessage::Ready) => {
+                self.start(game);
+            }
+            // board changed while bot was thinking
+            (State::WaitSuggestion, BotMessage::Suggestion { .. }) if !self.in_sync(game) => {
+                self.restart(game);
+            }
+            (State::WaitSuggestion, BotMessage::Suggestion { moves }) => {
+                for mv in moves {
+                    if let Some((inputs, target)) = self.plan(game, &mv) {
+                        self.play(mv, &target, &game.board);
+                        return Some(inputs);
+                    }
+                }
+                // none of the suggestions are reachable, drop piece and resync
+                self.send(&FrontendMessage::Stop);
+                self.state = State::Restart;
+                return Some(vec![ControlKind::HardDrop]);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn start(&mut self, game: &Game) {
+        self.queue = full_queue(game);
+        self.hold = game.hold;
+        self.board = board_to_tbp(&game.board);
+        self.send(&FrontendMessage::Start {
+            hold: self.hold,
+            queue: self.queue.clone(),
+            combo: game.combo,
+            back_to_back: game.back_to_back,
+            board: self.board.clone(),
+        });
+        self.state = State::Idle;
+    }
+
+    /// stop bot and start it again from current game
+    fn restart(&mut self, game: &Game) {
+        self.send(&FrontendMessage::Stop);
+        self.start(game);
+    }
+
+    /// false when game changed in a way bot wasn't told about,
+    /// like garbage rising, undo or cycling pieces
+    fn in_sync(&self, game: &Game) -> bool {
+        self.hold == game.hold
+            && full_queue(game).starts_with(&self.queue)
+            && board_to_tbp(&game.board) == self.board
+    }
+
+    /// tell bot about pieces revealed since last sync
+    fn send_new_pieces(&mut self, game: &Game) {
+        let queue = full_queue(game);
+        if queue.len() <= self.queue.len() {
+            return;
+        }
+        for &piece in &queue[self.queue.len()..] {
+            self.queue.push(piece);
+            self.send(&FrontendMessage::NewPiece { piece });
+        }
+    }
+
+    /// update known queue and board as bot does after `play`
+    fn play(&mut self, mv: Move, target: &Mino, board: &Board) {
+        let mut board = *board;
+        for (x, y) in target.get_cells() {
+            board.grid[y as usize][x as usize] = Cell::Mino(target.mino_type);
+        }
+        let full_rows = board.full_rows();
+        board.remove_rows(&full_rows);
+        self.board = board_to_tbp(&board);
+
+        let piece = mv.location.kind;
+        if self.queue.first() != Some(&piece) {
+            let current = self.queue.remove(0);
+            if self.hold.replace(current).is_none() {
+                self.queue.remove(0);
+            }
+        } else {
+            self.queue.remove(0);
+        }
+        self.send(&FrontendMessage::Play { mv });
+        self.state = State::Idle;
+    }
+
+    /// translate suggested move to inputs, along with piece where it locks
+    fn plan(&self, game: &Game, mv: &Move) -> Option<(Vec<ControlKind>, Mino)> {
+        let location = &mv.location;
+        let mut inputs = Vec::new();
+        let start = if location.kind == game.player.mino_type {
+            game.player.clone()
+        } else {
+            let next = game.hold.or_else(|| game.bags.list.last().copied())?;
+            if next != location.kind {
+                return None;
+            }
+            inputs.push(ControlKind::Hold);
+            Mino::new(next, game.rotation, &game.pieces, &game.board)
+        };
+        let mut target = Mino::new(location.kind, game.rotation, &game.pieces, &game.board);
+        target.direction = location.orientation.into();
+        target.x = location.x;
+        target.y = location.y;
+        target.update_ghost_y(&game.board);
+        if target.collides(&game.board) || !target.is_bottom() {
+            return None;
+        }
+        inputs.extend(find_path(&start, &target, &game.board)?);
+        Some((inputs, target))
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        self.send(&FrontendMessage::Quit);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// current piece followed by visible next pieces
+fn full_queue(game: &Game) -> Vec<MinoType> {
+    let mut queue = vec![game.player.mino_type];
+    queue.extend(game.bags.list.iter().rev());
+    queue
+}
+
+fn board_to_tbp(board: &Board) -> TbpBoard {
+    let mut rows: Vec<_> = board
+        .grid
+        .iter()
+        .map(|row| {
+            row.map(|cell| match cell {
+                Cell::Mino(mino_type) => Some(mino_type.to_char().unwrap_or('G')),
+                Cell::_Garbage => Some('G'),
+                Cell::Ghost | Cell::Empty => None,
+            })
+        })
+        .collect();
+    rows.resize(TBP_BOARD_HEIGHT, [None; GRID_WIDTH as usize]);
+    rows
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::handle_control;
+
+    /// handshake of stub bot, `expect` replies an error when a message comes out of order
+    const STUB_START: &str = r#"
+expect() {
+    while read -r line; do
+        case "$line" in
+            *'"type":"new_piece"'*) ;;
+            *"\"type\":\"$1\""*) return ;;
+            *) echo "{\"type\":\"error\",\"reason\":\"expected $1, got $line\"}"; exit 1 ;;
+        esac
+    done
+    exit 1
+}
+echo '{"type":"info","name":"stub","version":"1","author":"tet-rs","features":[]}'
+expect rules
+echo '{"type":"ready"}'
+expect start
+expect suggest
+echo "$1"
+expect play
+"#;
+
+    /// spawn stub bot running `script` after handshake and first suggestion,
+    /// it stops with "done" error when script ends
+    fn stub_bot(script: &str, suggestions: &[&str]) -> Bot {
+        let script = format!(
+            "{}{}\necho '{{\"type\":\"error\",\"reason\":\"done\"}}'",
+            STUB_START, script
+        );
+        let mut command: Vec<String> = vec!["sh".into(), "-c".into(), script, "stub".into()];
+        command.extend(suggestions.iter().map(|s| s.to_string()));
+        Bot::spawn(&command).unwrap()
+    }
+
+    fn suggestion(location: PieceLocation) -> String {
+        let mv = Move {
+            location,
+            spin: Spin::None,
+        };
+        format!(
+            r#"{{"type":"suggestion","moves":[{}]}}"#,
+            serde_json::to_string(&mv).unwrap()
+        )
+    }
+
+    fn poll_until(bot: &mut Bot, game: &Game, done: impl Fn(&Bot) -> bool) -> Vec<ControlKind> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(inputs) = bot.poll(game) {
+                return inputs;
+            }
+            if done(bot) {
+                return Vec::new();
+            }
+            assert!(Instant::now() < deadline, "bot stuck in {:?}", bot.state);
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// suggestion to drop current piece straight down
+    fn drop_suggestion(game: &Game) -> String {
+        let ghost = game.player.get_ghost();
+        suggestion(PieceLocation {
+            kind: game.player.mino_type,
+            orientation: Orientation::North,
+            x: ghost.x,
+            y: ghost.y,
+        })
+    }
+
+    /// poll until bot sends inputs for its first suggestion and play them
+    fn play_first_move(bot: &mut Bot, game: &mut Game) {
+        let inputs = poll_until(bot, game, |_| false);
+        assert_eq!(inputs.last(), Some(&ControlKind::HardDrop));
+        for control in inputs {
+            handle_control(game, control, Instant::now());
+        }
+        assert_eq!(game.stats.pieces, 1);
+        game.update_spawn_delay(Duration::from_secs(1));
+    }
+
+    #[test]
+    fn plays_suggestion_and_rejects_unreachable_one() {
+        let mut game = Game::new();
+        let reachable = drop_suggestion(&game);
+        // floating in the air, so it can't be where the piece locks
+        let next = *game.bags.list.last().unwrap();
+        let unreachable = suggestion(PieceLocation {
+            kind: next,
+            orientation: Orientation::North,
+            x: 4,
+            y: 15,
+        });
+        let script = r#"
+expect suggest
+echo "$2"
+expect stop
+expect start"#;
+        let mut bot = stub_bot(script, &[&reachable, &unreachable]);
+        play_first_move(&mut bot, &mut game);
+
+        let inputs = poll_until(&mut bot, &game, |_| false);
+        assert_eq!(inputs, vec![ControlKind::HardDrop]);
+        assert!(matches!(bot.state, State::Restart));
+        poll_until(&mut bot, &game, |bot| bot.error().is_some());
+        assert_eq!(bot.name.as_deref(), Some("stub 1 by tet-rs"));
+        assert_eq!(bot.error(), Some("done"));
+    }
+    #[test]
+    fn restarts_bot_when_garbage_rises() {
+        let mut game = Game::new();
+        let reachable = drop_suggestion(&game);
+        let script = r#"
+expect stop
+expect start"#;
+        let mut bot = stub_bot(script, &[&reachable]);
+        play_first_move(&mut bot, &mut game);
+        game.board.push_garbage(0);
+
+        poll_until(&mut bot, &game, |bot| bot.error().is_some());
+        assert_eq!(bot.error(), Some("done"));
+        assert_eq!(bot.board, board_to_tbp(&game.board));
+    }
+}
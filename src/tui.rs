@@ -1,11 +1,18 @@
-use std::io;
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use crossterm::{
+    cursor::Show,
     event::{
-        DisableMouseCapture, EnableMouseCapture, KeyboardEnhancementFlags,
-        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
     },
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
 use tui::{
@@ -13,36 +20,151 @@ use tui::{
     layout::Rect,
     style::{Color, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, Paragraph},
-    Terminal,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame, Terminal,
 };
 
-use crate::core::{Bag, Cell, Game, MinoType, GRID_HEIGHT, GRID_WIDTH};
+use crate::{
+    config::{Config, StatsPanel, Visual},
+    core::{Cell, Game, MinoType, GRID_HEIGHT, GRID_WIDTH},
+    editor::{Editor, Focus},
+    finesse::Finesse,
+    keymap::{Keymap, Rebind, ACTIONS, PRESET_NAMES},
+    menu::Menu,
+    mode::{format_time, Mode},
+    pieces::{self, Piece},
+    solver::Browser,
+    ControlKind,
+};
 
 const CELL_WIDTH: u16 = 3;
+const MODE_WIDTH: u16 = 28;
+const KEYS_WIDTH: u16 = 40;
+const MENU_WIDTH: u16 = 50;
+
+pub const THEME_NAMES: [&str; 3] = ["default", "guideline", "mono"];
+
+/// colors of pieces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// terminal colors
+    #[default]
+    Default,
+    /// guideline colors, needs true color terminal
+    Guideline,
+    /// every piece in gray
+    Mono,
+}
+
+impl Theme {
+    pub fn name(&self) -> &'static str {
+        THEME_NAMES[*self as usize]
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Some(match name.to_lowercase().as_str() {
+            "default" => Theme::Default,
+            "guideline" => Theme::Guideline,
+            "mono" => Theme::Mono,
+            _ => return None,
+        })
+    }
+}
+
+/// first menu row shown, scrolled so selected row is visible
+fn menu_offset(cursor: usize) -> usize {
+    cursor.saturating_sub(GRID_HEIGHT as usize - 1)
+}
+
+fn text_lines(lines: Vec<String>) -> Vec<Spans<'static>> {
+    lines.into_iter().map(Spans::from).collect()
+}
+
+fn finesse_lines(finesse: &Finesse) -> Vec<String> {
+    vec![
+        format!("Finesse: {:.1}%", finesse.percent()),
+        format!("Faults: {}", finesse.faults),
+    ]
+}
+
+/// lines of stats panel, only elements shown in config
+fn stats_lines(game: &Game, finesse: &Finesse, shown: &StatsPanel) -> Vec<String> {
+    let stats = &game.stats;
+    let mut lines = Vec::new();
+    if shown.time {
+        lines.push(format!("Time   {}", format_time(stats.elapsed)));
+    }
+    if shown.pieces {
+        lines.push(format!("Pieces {}", stats.pieces));
+    }
+    if shown.pps {
+        lines.push(format!("PPS    {:.2}", stats.pps()));
+    }
+    if shown.lines {
+        lines.push(format!("Lines  {}", stats.lines));
+    }
+    if shown.score {
+        lines.push(format!("Score  {}", game.score));
+    }
+    if shown.level {
+        lines.push(format!("Level  {}", game.level));
+    }
+    if shown.apm {
+        lines.push(format!("APM    {:.1}", stats.apm()));
+    }
+    if shown.kpp {
+        lines.push(format!("KPP    {:.2}", stats.kpp()));
+    }
+    if shown.finesse {
+        lines.push(format!(
+            "Faults {} ({:.1}%)",
+            finesse.faults,
+            finesse.percent()
+        ));
+    }
+    lines
+}
+
+fn mino_color(mino_type: MinoType, theme: Theme, pieces: &[Piece]) -> Color {
+    match (theme, mino_type) {
+        (Theme::Mono, _) => Color::Gray,
+        (Theme::Guideline, MinoType::I) => Color::Rgb(0x00, 0xf0, 0xf0),
+        (Theme::Guideline, MinoType::J) => Color::Rgb(0x00, 0x00, 0xf0),
+        (Theme::Guideline, MinoType::L) => Color::Rgb(0xf0, 0xa0, 0x00),
+        (Theme::Guideline, MinoType::O) => Color::Rgb(0xf0, 0xf0, 0x00),
+        (Theme::Guideline, MinoType::S) => Color::Rgb(0x00, 0xf0, 0x00),
+        (Theme::Guideline, MinoType::T) => Color::Rgb(0xa0, 0x00, 0xf0),
+        (Theme::Guideline, MinoType::Z) => Color::Rgb(0xf0, 0x00, 0x00),
+        (_, MinoType::Custom(index)) => {
+            let (r, g, b) = pieces[index as usize].color;
+            Color::Rgb(r, g, b)
+        }
+        (_, MinoType::I) => Color::LightBlue,
+        (_, MinoType::J) => Color::Blue,
+        (_, MinoType::L) => Color::LightYellow,
+        (_, MinoType::O) => Color::Yellow,
+        (_, MinoType::S) => Color::LightGreen,
+        (_, MinoType::T) => Color::Magenta,
+        (_, MinoType::Z) => Color::Red,
+    }
+}
 
 // trait GetSpans {
 //     fn get_spans(&self) -> Vec<Span>;
 // }
 
 impl Cell {
-    fn get_spans(&self) -> Vec<Span<'static>> {
+    fn get_spans(&self, theme: Theme, pieces: &[Piece]) -> Vec<Span<'static>> {
         // let cube_text = "+";
         let cube_text = "   ";
         let empty_text = "   ";
         let s = Style::default();
         match self {
             Cell::Mino(minotype) => {
-                let style = match minotype {
-                    MinoType::I => s.bg(Color::LightBlue),
-                    MinoType::J => s.bg(Color::Blue),
-                    MinoType::L => s.bg(Color::LightYellow),
-                    MinoType::O => s.bg(Color::Yellow),
-                    MinoType::S => s.bg(Color::LightGreen),
-                    MinoType::T => s.bg(Color::Magenta),
-                    MinoType::Z => s.bg(Color::Red),
-                };
-                vec![Span::styled(cube_text, style)]
+                vec![Span::styled(
+                    cube_text,
+                    s.bg(mino_color(*minotype, theme, pieces)),
+                )]
             }
             Cell::Ghost => vec![Span::styled(cube_text, s.bg(Color::Black))],
             Cell::_Garbage => vec![Span::styled(cube_text, s.bg(Color::Gray))],
@@ -52,22 +174,57 @@ impl Cell {
 }
 
 trait ToSpans {
-    fn get_spans(&self) -> Vec<Spans<'static>>;
+    fn get_spans(&self, theme: Theme, pieces: &[Piece]) -> Vec<Spans<'static>>;
+}
+
+/// names of keys bound to `control`
+fn key_names(keymap: &Keymap, control: ControlKind) -> Vec<String> {
+    keymap
+        .keys_of(control)
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// left, bottom, right and top of cells drawn in hold and next boxes
+/// tetrominoes fit in 4x2, other pieces grow it
+fn piece_bounds(cells: &[(i8, i8)]) -> (i8, i8, i8, i8) {
+    let left = cells.iter().map(|&(x, _)| x).min().unwrap_or(0).min(-1);
+    let bottom = cells.iter().map(|&(_, y)| y).min().unwrap_or(0).min(0);
+    let right = cells.iter().map(|&(x, _)| x).max().unwrap_or(0).max(2);
+    let top = cells.iter().map(|&(_, y)| y).max().unwrap_or(0).max(1);
+    (left, bottom, right, top)
+}
+
+/// columns and rows every piece of `pieces` fits in
+fn piece_box(pieces: &[Piece]) -> (u16, u16) {
+    pieces::mino_types(pieces)
+        .iter()
+        .map(|mino_type| piece_bounds(mino_type.get_cells(pieces)))
+        .fold((0, 0), |(width, height), (left, bottom, right, top)| {
+            (
+                width.max((right - left + 1) as u16),
+                height.max((top - bottom + 1) as u16),
+            )
+        })
 }
 
 impl ToSpans for Option<MinoType> {
-    fn get_spans(&self) -> Vec<Spans<'static>> {
-        let mut grid = [[Cell::Empty; 4]; 2];
+    fn get_spans(&self, theme: Theme, pieces: &[Piece]) -> Vec<Spans<'static>> {
+        let cells = self.map_or(&[][..], |mino_type| mino_type.get_cells(pieces));
+        let (left, bottom, right, top) = piece_bounds(cells);
+        let mut grid =
+            vec![vec![Cell::Empty; (right - left + 1) as usize]; (top - bottom + 1) as usize];
         if let Some(mino_type) = self {
-            mino_type.get_cells().into_iter().for_each(|(x, y)| {
-                grid[*y as usize][(*x + 1) as usize] = Cell::Mino(*mino_type);
+            cells.iter().for_each(|(x, y)| {
+                grid[(*y - bottom) as usize][(*x - left) as usize] = Cell::Mino(*mino_type);
             });
         }
         let mut grid_text = Vec::new();
         for line in grid.into_iter().rev() {
             let mut line_spans = Vec::new();
             for cell in line {
-                let cell_spans = cell.get_spans();
+                let cell_spans = cell.get_spans(theme, pieces);
                 line_spans.extend(cell_spans.into_iter());
             }
             grid_text.push(Spans::from(line_spans))
@@ -76,91 +233,537 @@ impl ToSpans for Option<MinoType> {
     }
 }
 
-impl ToSpans for Bag {
-    fn get_spans(&self) -> Vec<Spans<'static>> {
+impl ToSpans for [MinoType] {
+    fn get_spans(&self, theme: Theme, pieces: &[Piece]) -> Vec<Spans<'static>> {
         let mut grid_text = Vec::new();
-        self.list.iter().rev().for_each(|mino_type| {
-            grid_text.extend(Some(*mino_type).get_spans().into_iter());
-            grid_text.extend((vec![Spans::from(Cell::Empty.get_spans())]).into_iter());
+        let (_, height) = piece_box(pieces);
+        self.iter().for_each(|mino_type| {
+            // shorter pieces are padded, so every piece takes same rows
+            let spans = Some(*mino_type).get_spans(theme, pieces);
+            let padding = (height as usize).saturating_sub(spans.len());
+            grid_text.extend(vec![Spans::default(); padding]);
+            grid_text.extend(spans);
+            grid_text.push(Spans::from(Cell::Empty.get_spans(theme, pieces)));
         });
-        return grid_text;
+        grid_text
+    }
+}
+
+/// board rows from top to bottom, cells in `marked` are marked
+/// `overlay` is drawn translucent over empty cells
+fn grid_spans(
+    grid: &[[Cell; GRID_WIDTH as usize]],
+    marked: &[(i8, i8)],
+    overlay: &[(i8, i8, MinoType)],
+    theme: Theme,
+    pieces: &[Piece],
+) -> Vec<Spans<'static>> {
+    let mut grid_text = Vec::new();
+    for (y, line) in grid.iter().enumerate().rev() {
+        let mut line_spans = Vec::new();
+        for (x, cell) in line.iter().enumerate() {
+            let cell_spans = cell.get_spans(theme, pieces);
+            let guide = overlay
+                .iter()
+                .find(|&&(ox, oy, _)| (ox, oy) == (x as i8, y as i8))
+                .filter(|_| matches!(cell, Cell::Empty | Cell::Ghost));
+            if let Some(&(_, _, mino_type)) = guide {
+                line_spans.extend(cell_spans.into_iter().map(|span| {
+                    Span::styled("░░░", span.style.fg(mino_color(mino_type, theme, pieces)))
+                }));
+            } else if marked.contains(&(x as i8, y as i8)) {
+                line_spans.extend(
+                    cell_spans
+                        .into_iter()
+                        .map(|span| Span::styled("[ ]", span.style)),
+                );
+            } else {
+                line_spans.extend(cell_spans);
+            }
+        }
+        grid_text.push(Spans::from(line_spans))
+    }
+    grid_text
+}
+
+pub const PAUSE_ITEMS: [&str; 3] = ["Resume", "Restart", "Quit"];
+
+/// key releases were asked from terminal, so flags are popped when leaving
+static KEY_RELEASE: AtomicBool = AtomicBool::new(false);
+/// terminal was given back to shell by `suspend`
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+/// screen has to be drawn from scratch after `resume`
+static REDRAW: AtomicBool = AtomicBool::new(false);
+
+fn enter_terminal() -> crossterm::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    stdout
+        .execute(EnterAlternateScreen)?
+        .execute(EnableMouseCapture)?
+        .execute(EnableFocusChange)?;
+    if KEY_RELEASE.load(Ordering::Relaxed) {
+        stdout.execute(PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+        ))?;
     }
+    Ok(())
+}
+
+fn leave_terminal() -> crossterm::Result<()> {
+    disable_raw_mode()?;
+    let mut stdout = io::stdout();
+    stdout
+        .execute(LeaveAlternateScreen)?
+        .execute(DisableMouseCapture)?
+        .execute(DisableFocusChange)?;
+    if KEY_RELEASE.load(Ordering::Relaxed) {
+        stdout.execute(PopKeyboardEnhancementFlags)?;
+    }
+    Ok(())
+}
+
+/// give terminal back to shell before the process is stopped
+pub fn suspend() -> crossterm::Result<()> {
+    if !SUSPENDED.swap(true, Ordering::Relaxed) {
+        leave_terminal()?;
+        io::stdout().execute(Show)?;
+    }
+    Ok(())
+}
+
+/// take terminal again after the process continued
+pub fn resume() -> crossterm::Result<()> {
+    if SUSPENDED.swap(false, Ordering::Relaxed) {
+        enter_terminal()?;
+    }
+    REDRAW.store(true, Ordering::Relaxed);
+    Ok(())
 }
 
 pub struct UI {
     pub terminal: Terminal<CrosstermBackend<std::io::Stdout>>,
+    pub visual: Visual,
+    /// message shown below HUD, like config errors
+    pub notice: Option<String>,
+    /// terminal reports key releases, otherwise held keys are guessed from key repeat
+    pub key_release: bool,
 }
 
 impl UI {
+    /// must be called before reading events, support of key releases is asked from terminal
     pub fn new() -> crossterm::Result<UI> {
         enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        stdout
-            .execute(EnterAlternateScreen)?
-            .execute(EnableMouseCapture)?
-            .execute(PushKeyboardEnhancementFlags(
-                KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
-            ))?;
-        let backend = CrosstermBackend::new(stdout);
-        return Ok(UI {
+        let key_release = supports_keyboard_enhancement().unwrap_or(false);
+        KEY_RELEASE.store(key_release, Ordering::Relaxed);
+        enter_terminal()?;
+        let backend = CrosstermBackend::new(io::stdout());
+        Ok(UI {
             terminal: Terminal::new(backend)?,
-        });
+            visual: Config::default().visual,
+            notice: (!key_release).then(|| {
+                "terminal doesn't report key releases, held keys are guessed from key repeat"
+                    .to_string()
+            }),
+            key_release,
+        })
     }
 
-    pub fn render(&mut self, game: &Game) -> crossterm::Result<()> {
+    pub fn render(
+        &mut self,
+        game: &Game,
+        mode: &dyn Mode,
+        solutions: Option<&Browser>,
+        finesse: &Finesse,
+        keymap: &Keymap,
+    ) -> crossterm::Result<()> {
         // create board widget
-        let mut grid = game.board.grid.clone();
-        game.player
-            .get_ghost()
-            .get_cells()
-            .into_iter()
-            .for_each(|(x, y)| {
-                grid[y as usize][x as usize] = Cell::Ghost;
-            });
-        game.player.get_cells().into_iter().for_each(|(x, y)| {
-            grid[y as usize][x as usize] = Cell::Mino(game.player.mino_type);
-        });
-        let mut grid_text = Vec::new();
-        for line in grid.into_iter().rev() {
-            let mut line_spans = Vec::new();
-            for cell in line {
-                let cell_spans = cell.get_spans();
-                line_spans.extend(cell_spans.into_iter());
+        let mut grid = game.board.grid;
+        let mut title = "TETRIS".to_string();
+        let mut marked = Vec::new();
+        if let Some(browser) = solutions {
+            title = browser.title();
+            if let Some(step) = browser.current() {
+                grid = step.board.grid;
+                marked = step.piece.get_cells().to_vec();
+                marked.iter().for_each(|&(x, y)| {
+                    grid[y as usize][x as usize] = Cell::Mino(step.piece.mino_type);
+                });
             }
-            grid_text.push(Spans::from(line_spans))
+        } else if game.is_active() {
+            if self.visual.ghost {
+                game.player
+                    .get_ghost()
+                    .get_cells()
+                    .into_iter()
+                    .for_each(|(x, y)| {
+                        grid[y as usize][x as usize] = Cell::Ghost;
+                    });
+            }
+            game.player.get_cells().into_iter().for_each(|(x, y)| {
+                grid[y as usize][x as usize] = Cell::Mino(game.player.mino_type);
+            });
         }
-        let board_widget = Paragraph::new(grid_text)
-            .block(Block::default().title("TETRIS").borders(Borders::all()));
+        let overlay = if solutions.is_some() {
+            Vec::new()
+        } else {
+            mode.overlay(game)
+        };
+        let theme = self.visual.theme;
+        let pieces = &game.pieces;
+        let board_widget = Paragraph::new(grid_spans(&grid, &marked, &overlay, theme, pieces))
+            .block(Block::default().title(title).borders(Borders::all()));
         let board_area = Rect::new(0, 0, GRID_WIDTH * CELL_WIDTH + 2, GRID_HEIGHT + 2);
 
-        let hover_widget = Paragraph::new(game.hold.get_spans())
+        // hold and next boxes fit largest piece of the set
+        let (box_width, box_height) = piece_box(pieces);
+        let box_width = box_width * CELL_WIDTH + 2;
+        let hover_widget = Paragraph::new(game.hold.get_spans(theme, pieces))
             .block(Block::default().title("Hold").borders(Borders::all()));
-        let hover_area = Rect::new(GRID_WIDTH * CELL_WIDTH + 2, 0, 4 * CELL_WIDTH + 2, 2 + 2);
+        let hover_area = Rect::new(GRID_WIDTH * CELL_WIDTH + 2, 0, box_width, box_height + 2);
 
-        let bag_widget = Paragraph::new(game.bags.get_spans())
+        let preview: Vec<MinoType> = game
+            .bags
+            .list
+            .iter()
+            .rev()
+            .take(self.visual.preview)
+            .copied()
+            .collect();
+        let bag_widget = Paragraph::new(preview.get_spans(theme, pieces))
             .block(Block::default().title("Next").borders(Borders::all()));
+        // next box is cut at bottom of board
+        let bag_y = box_height + 2;
         let bag_area = Rect::new(
+            GRID_WIDTH * CELL_WIDTH + 2,
+            bag_y,
+            box_width,
+            ((box_height + 1) * self.visual.preview as u16 + 2).min(GRID_HEIGHT + 2 - bag_y),
+        );
+
+        let mut hud = mode.hud(game);
+        let controls: Vec<String> = mode
+            .controls()
+            .into_iter()
+            .filter_map(|(control, action)| {
+                let keys = key_names(keymap, control);
+                (!keys.is_empty()).then(|| format!("{:<5} {}", keys.join("/"), action))
+            })
+            .collect();
+        if !controls.is_empty() {
+            hud.push(String::new());
+            hud.extend(controls);
+        }
+        if let Some(notice) = &self.notice {
+            hud.extend([String::new(), notice.clone()]);
+        }
+        let mode_widget = Paragraph::new(text_lines(hud))
+            .block(Block::default().title(mode.name()).borders(Borders::all()))
+            .wrap(Wrap { trim: false });
+
+        // stats panel takes bottom of mode column
+        let stats = stats_lines(game, finesse, &self.visual.stats);
+        let stats_height = if stats.is_empty() {
+            0
+        } else {
+            stats.len() as u16 + 2
+        };
+        let stats_widget = Paragraph::new(text_lines(stats))
+            .block(Block::default().title("Stats").borders(Borders::all()));
+        let column_x = GRID_WIDTH * CELL_WIDTH + 2 + box_width;
+        let mode_area = Rect::new(column_x, 0, MODE_WIDTH, GRID_HEIGHT + 2 - stats_height);
+        let stats_area = Rect::new(
+            column_x,
+            GRID_HEIGHT + 2 - stats_height,
+            MODE_WIDTH,
+            stats_height,
+        );
+
+        self.draw(|f| {
+            f.render_widget(board_widget, board_area);
+            f.render_widget(hover_widget, hover_area);
+            if !preview.is_empty() {
+                f.render_widget(bag_widget, bag_area);
+            }
+            f.render_widget(mode_widget, mode_area);
+            if stats_height > 0 {
+                f.render_widget(stats_widget, stats_area);
+            }
+        })?;
+        Ok(())
+    }
+
+    pub fn render_editor(&mut self, editor: &Editor) -> crossterm::Result<()> {
+        let title = if editor.pages.len() > 1 {
+            format!("EDITOR {}/{}", editor.page + 1, editor.pages.len())
+        } else {
+            "EDITOR".to_string()
+        };
+        let theme = self.visual.theme;
+        // editor only has tetrominoes
+        let pieces: &[Piece] = &[];
+        let board_widget = Paragraph::new(grid_spans(
+            &editor.board.grid,
+            &[editor.cursor],
+            &[],
+            theme,
+            pieces,
+        ))
+        .block(Block::default().title(title).borders(Borders::all()));
+        let board_area = Rect::new(0, 0, GRID_WIDTH * CELL_WIDTH + 2, GRID_HEIGHT + 2);
+
+        let focused = |title: &'static str, focus: Focus| {
+            let block = Block::default().title(title).borders(Borders::all());
+            if editor.focus == focus {
+                block.border_style(Style::default().fg(Color::Yellow))
+            } else {
+                block
+            }
+        };
+        let hold_widget = Paragraph::new(editor.hold.get_spans(theme, pieces))
+            .block(focused("Hold", Focus::Hold));
+        let hold_area = Rect::new(GRID_WIDTH * CELL_WIDTH + 2, 0, 4 * CELL_WIDTH + 2, 2 + 2);
+
+        let queue_widget = Paragraph::new(editor.queue.get_spans(theme, pieces))
+            .block(focused("Queue", Focus::Queue));
+        let queue_area = Rect::new(
             GRID_WIDTH * CELL_WIDTH + 2,
             4,
             4 * CELL_WIDTH + 2,
-            3 * 6 + 2,
+            GRID_HEIGHT + 2 - 4,
         );
 
-        self.terminal.draw(|f| {
+        let mut brush = vec![Span::raw("Brush ")];
+        brush.extend(editor.brush.get_spans(theme, pieces));
+        let mut help = vec![Spans::from(brush), Spans::default()];
+        help.extend(text_lines(
+            [
+                "hjkl/arrows move cursor",
+                "space       paint",
+                "x/backspace erase",
+                "I J L O S T Z",
+                "            brush, queue",
+                "            or hold piece",
+                "G           garbage brush",
+                "tab         switch focus",
+                "backspace   remove piece",
+                "c           clear board",
+                "[ ]         fumen page",
+                "mouse       paint/erase",
+                "enter       start",
+                "q/esc       quit",
+            ]
+            .map(String::from)
+            .to_vec(),
+        ));
+        let help_widget = Paragraph::new(help).block(focused("Board", Focus::Board));
+        let help_area = Rect::new(
+            GRID_WIDTH * CELL_WIDTH + 2 + 4 * CELL_WIDTH + 2,
+            0,
+            MODE_WIDTH,
+            GRID_HEIGHT + 2,
+        );
+
+        self.draw(|f| {
             f.render_widget(board_widget, board_area);
-            f.render_widget(hover_widget, hover_area);
-            f.render_widget(bag_widget, bag_area);
+            f.render_widget(hold_widget, hold_area);
+            f.render_widget(queue_widget, queue_area);
+            f.render_widget(help_widget, help_area);
         })?;
-        return Ok(());
+        Ok(())
+    }
+
+    pub fn render_rebind(&mut self, rebind: &Rebind) -> crossterm::Result<()> {
+        let title = match rebind.preset {
+            Some(preset) => format!("KEYS ({})", PRESET_NAMES[preset]),
+            None => "KEYS".to_string(),
+        };
+        let rows: Vec<Spans> = ACTIONS
+            .iter()
+            .enumerate()
+            .map(|(index, (_, name))| {
+                let selected = index == rebind.cursor;
+                let keys = if selected && rebind.capturing {
+                    "press a key...".to_string()
+                } else {
+                    let keys: Vec<String> = rebind
+                        .keymap
+                        .keys(index)
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    keys.join(", ")
+                };
+                let line = format!("{:<14}{}", name, keys);
+                if selected {
+                    Spans::from(Span::styled(line, Style::default().fg(Color::Yellow)))
+                } else {
+                    Spans::from(line)
+                }
+            })
+            .collect();
+        let keys_widget =
+            Paragraph::new(rows).block(Block::default().title(title).borders(Borders::all()));
+        let keys_area = Rect::new(0, 0, KEYS_WIDTH, ACTIONS.len() as u16 + 2);
+
+        let help = text_lines(
+            [
+                "jk/arrows   select",
+                "enter       add key",
+                "backspace   clear keys",
+                "tab         next preset",
+                "w           save",
+                "q/esc       cancel",
+            ]
+            .map(String::from)
+            .to_vec(),
+        );
+        let help_widget =
+            Paragraph::new(help).block(Block::default().title("Help").borders(Borders::all()));
+        let help_area = Rect::new(KEYS_WIDTH, 0, MODE_WIDTH, ACTIONS.len() as u16 + 2);
+
+        self.draw(|f| {
+            f.render_widget(keys_widget, keys_area);
+            f.render_widget(help_widget, help_area);
+        })?;
+        Ok(())
+    }
+
+    pub fn render_menu(&mut self, menu: &Menu, config: &Config) -> crossterm::Result<()> {
+        let offset = menu_offset(menu.cursor);
+        let rows: Vec<Spans> = menu
+            .items(config)
+            .into_iter()
+            .enumerate()
+            .skip(offset)
+            .map(|(index, item)| {
+                if index == menu.cursor {
+                    Spans::from(Span::styled(
+                        format!("> {}", item),
+                        Style::default().fg(Color::Yellow),
+                    ))
+                } else {
+                    Spans::from(format!("  {}", item))
+                }
+            })
+            .collect();
+        let menu_widget = Paragraph::new(rows)
+            .block(Block::default().title(menu.title()).borders(Borders::all()));
+        let menu_area = Rect::new(0, 0, MENU_WIDTH, GRID_HEIGHT + 2);
+
+        let mut help: Vec<String> = menu.help().into_iter().map(String::from).collect();
+        if let Some(notice) = &menu.notice {
+            help.extend([String::new(), notice.clone()]);
+        }
+        let help_widget = Paragraph::new(text_lines(help))
+            .block(Block::default().title("Help").borders(Borders::all()))
+            .wrap(Wrap { trim: false });
+        let help_area = Rect::new(MENU_WIDTH, 0, MODE_WIDTH, GRID_HEIGHT + 2);
+
+        self.draw(|f| {
+            f.render_widget(menu_widget, menu_area);
+            f.render_widget(help_widget, help_area);
+        })?;
+        Ok(())
+    }
+
+    /// menu row of terminal cell, counted from first item
+    pub fn menu_row(&self, column: u16, row: u16, cursor: usize) -> Option<usize> {
+        if column == 0 || column >= MENU_WIDTH - 1 || row == 0 || row > GRID_HEIGHT {
+            return None;
+        }
+        Some(row as usize - 1 + menu_offset(cursor))
+    }
+
+    /// board position of terminal cell, None when it's outside of board
+    pub fn board_cell(&self, column: u16, row: u16) -> Option<(i8, i8)> {
+        let x = column.checked_sub(1)? / CELL_WIDTH;
+        let y = GRID_HEIGHT.checked_sub(row)?;
+        if row == 0 || x >= GRID_WIDTH || y >= GRID_HEIGHT {
+            return None;
+        }
+        Some((x as i8, y as i8))
+    }
+
+    /// show results after game ended
+    pub fn render_results(
+        &mut self,
+        game: &Game,
+        mode: &dyn Mode,
+        finesse: &Finesse,
+        keymap: &Keymap,
+    ) -> crossterm::Result<()> {
+        let title = if game.over { "GAME OVER" } else { "FINISHED" };
+        let mut lines = vec![mode.name(), String::new()];
+        lines.extend(mode.results(game));
+        lines.extend(finesse_lines(finesse));
+        lines.push(String::new());
+        for (control, action) in [
+            (ControlKind::Quit, "quit"),
+            (ControlKind::Restart, "restart"),
+        ] {
+            let keys = key_names(keymap, control);
+            if !keys.is_empty() {
+                lines.push(format!("press {} to {}", keys.join(" or "), action));
+            }
+        }
+        let results_widget = Paragraph::new(text_lines(lines))
+            .block(Block::default().title(title).borders(Borders::all()));
+        let results_area = Rect::new(0, 0, GRID_WIDTH * CELL_WIDTH + 2, GRID_HEIGHT + 2);
+
+        self.draw(|f| {
+            f.render_widget(Clear, results_area);
+            f.render_widget(results_widget, results_area);
+        })?;
+        Ok(())
+    }
+
+    /// pause menu drawn over board, board is hidden while paused
+    pub fn render_pause(&mut self, cursor: usize) -> crossterm::Result<()> {
+        let mut lines = vec![Spans::default()];
+        lines.extend(PAUSE_ITEMS.iter().enumerate().map(|(index, item)| {
+            if index == cursor {
+                Spans::from(Span::styled(
+                    format!("> {}", item),
+                    Style::default().fg(Color::Yellow),
+                ))
+            } else {
+                Spans::from(format!("  {}", item))
+            }
+        }));
+        let pause_widget =
+            Paragraph::new(lines).block(Block::default().title("PAUSED").borders(Borders::all()));
+        let pause_area = Rect::new(0, 0, GRID_WIDTH * CELL_WIDTH + 2, GRID_HEIGHT + 2);
+
+        self.draw(|f| {
+            f.render_widget(Clear, pause_area);
+            f.render_widget(pause_widget, pause_area);
+        })?;
+        Ok(())
+    }
+
+    /// pause menu row of terminal cell
+    pub fn pause_row(&self, column: u16, row: u16) -> Option<usize> {
+        let index = (row as usize).checked_sub(2)?;
+        if column == 0 || column > GRID_WIDTH * CELL_WIDTH || index >= PAUSE_ITEMS.len() {
+            return None;
+        }
+        Some(index)
+    }
+
+    /// draw frame, from scratch when terminal was taken back after suspend
+    fn draw<F>(&mut self, f: F) -> crossterm::Result<()>
+    where
+        F: FnOnce(&mut Frame<CrosstermBackend<io::Stdout>>),
+    {
+        if REDRAW.swap(false, Ordering::Relaxed) {
+            self.terminal.clear()?;
+        }
+        self.terminal.draw(f)?;
+        Ok(())
     }
 
     pub fn exit(&mut self) -> crossterm::Result<()> {
-        disable_raw_mode()?;
-        self.terminal
-            .backend_mut()
-            .execute(LeaveAlternateScreen)?
-            .execute(DisableMouseCapture)?
-            .execute(PopKeyboardEnhancementFlags)?;
-        return Ok(());
+        leave_terminal()
     }
 }